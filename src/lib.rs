@@ -6,6 +6,16 @@ pub use process::FileProcessor;
 #[cfg(feature = "pandoc")]
 pub use process::PandocProcessor;
 
-pub use process::{Error, PlaintextProcessor};
+pub use process::{
+    AmbiguousProcessor, Error, ExplainFormat, ExplainProcessor, PlaintextProcessor, ReviewProcessor,
+};
 
-pub use transliterate::{Charset, Transliterator};
+pub use transliterate::{
+    detect_charset, find_confusables, mine_exceptions, Alignment, AmbiguousDigraph, Charmap,
+    Charset, Confusable, Confusables, Detection, Evaluation, ExceptionList, ExceptionMatch,
+    MinedException, Mismatch, MismatchCause, Normalization, ProtectedTerms, Span,
+    TransliteratingReader, TransliteratingWriter, Transliterator, TransliteratorBuilder,
+    WordAction, WordReport,
+};
+
+pub use transliterate::Error as TransliterationError;
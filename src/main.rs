@@ -1,13 +1,16 @@
+use std::io::{self, BufRead, Read};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
-use std::{env, error, fmt, path};
-
-mod process;
-mod transliterate;
+use std::sync::Arc;
+use std::{env, error, fmt, fs, path};
 
 #[cfg(feature = "pandoc")]
-use process::PandocProcessor;
-use process::{FileProcessor, PlaintextProcessor};
-use transliterate::{Charset, Transliterator};
+use translitrs::PandocProcessor;
+use translitrs::{
+    find_confusables, mine_exceptions, AmbiguousProcessor, Charmap, Charset, Evaluation,
+    ExceptionList, ExplainFormat, ExplainProcessor, FileProcessor, MismatchCause, Normalization,
+    PlaintextProcessor, ProtectedTerms, ReviewProcessor, Transliterator,
+};
 
 fn version() {
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"),);
@@ -18,6 +21,12 @@ fn help() {
     println!();
     println!("USAGE:");
     println!("  {} [OPTIONS]", env!("CARGO_PKG_NAME"));
+    println!("  {} confusables [<string>...]", env!("CARGO_PKG_NAME"));
+    println!("  {} mine-exceptions [<path>...]", env!("CARGO_PKG_NAME"));
+    println!(
+        "  {} eval --latin <path> --cyrillic <path> [-e <path>...] [--json]",
+        env!("CARGO_PKG_NAME")
+    );
     println!("  pandoc --filter {} [...]", env!("CARGO_PKG_NAME"));
     println!();
     println!("OPTIONS:");
@@ -26,28 +35,81 @@ fn help() {
     println!("  -o, --output <path>     write output to file");
     println!("                          default: stdout");
     println!("  -f, --from <charset>    convert from character set");
-    println!("                          default: latin");
+    println!("                          default: latin, detect with auto");
     println!("  -t, --into <charset>    convert to character set");
     println!("                          default: cyrillic");
+    println!("  -m, --mixed-scripts     convert words of both scripts, ignoring --from");
+    println!("  -r, --repair-homoglyphs repair look-alike letters of the other script");
     println!("  -d, --skip-digraph      do not check for digraph exceptions");
+    println!("  -z, --round-trip        mark split digraphs with zero width non-joiner");
+    println!("                          and read unmarked ones as digraphs");
+    println!("  -g, --ignorable <list>  skip these code points when matching letters");
+    println!("                          eg. 00AD,0300-036F, empty to match all");
+    println!("  -A, --accents           read accented vowels and syllabic r as base letter");
+    println!("  -n, --normalize <form>  write output in Unicode normal form");
+    println!("                          forms: nfc, nfd, none (default)");
     println!("  -u, --force-foreign     process words with foreign and mixed characters");
+    println!("  -s, --suffixes          keep Latin words followed by a hyphen and a");
+    println!("                          case suffix, converting the suffix");
     println!("  -l, --force-links       process hyperlinks, email addresses and units");
+    println!("  -e, --exceptions <path> load additional digraph exceptions from file");
+    println!("  -P, --prefix-rules      split digraphs after prefixes in words which no");
+    println!("                          digraph exception covers");
+    println!("  -k, --keep-terms        keep built-in brand names and acronyms untouched");
+    println!("  -K, --terms <path>      load additional protected terms from file");
+    println!("  -C, --terms-ignore-case match protected terms regardless of letter case");
+    println!("  -S, --terms-suffixes    match protected terms followed by a case suffix");
+    println!("  -c, --charmap <path>    use custom TOML or JSON charmap");
+    println!("                          overrides --from and --into");
+    println!("  -x, --explain <format>  print how each word is transliterated");
+    println!("                          formats: text, json");
+    println!("  -a, --ambiguous         list words with digraphs at a prefix boundary");
+    println!("                          which no digraph exception applies to");
+    println!("  -R, --review <path>     ask how to read ambiguous digraphs and append");
+    println!("                          decisions to exception file, requires --input");
     #[cfg(feature = "pandoc")]
     println!("  -p, --pandoc-filter     run in Pandoc JSON pipe filter mode");
     println!("  -v, --version           show version and quit");
     println!("  -h, --help              show usage help and quit");
     println!();
+    println!("Confusables:");
+    println!("  Print strings mixing Latin and Cyrillic letters, read from arguments");
+    println!("  or lines of stdin, with their single-script skeleton and byte ranges");
+    println!("  of offending letters. Exits with status 1 if any string is mixed.");
+    println!();
+    println!("Mine exceptions:");
+    println!("  Print digraph exception list derived from Cyrillic corpus files, or");
+    println!("  stdin, with the number of words covered by each exception.");
+    println!();
+    println!("Eval:");
+    println!("  Transliterate aligned Latin and Cyrillic texts in both directions and");
+    println!("  print word accuracy, mismatch counts by cause and each mismatch.");
+    println!();
     println!("Character sets:");
     println!("  latin,    lat,  l       Serbian Latin");
     println!("  latin8,   lat8, l8      Serbian Latin (Unicode)");
     println!("  cyrillic, cyr,  c       Serbian Cyrillic");
     println!();
     println!("Pandoc filter environment variables:");
-    println!("  CHARS_FROM=<charset>");
+    println!("  CHARS_FROM=<charset|auto>");
     println!("  CHARS_INTO=<charset>");
+    println!("  MIXED_SCRIPTS");
+    println!("  REPAIR_HOMOGLYPHS");
     println!("  SKIP_DIGRAPH");
+    println!("  ROUND_TRIP");
+    println!("  IGNORABLE=<list>");
+    println!("  ACCENTS");
+    println!("  NORMALIZE=<form>");
     println!("  FORCE_FOREIGN");
     println!("  FORCE_LINKS");
+    println!("  FOREIGN_SUFFIXES");
+    println!("  DIGRAPH_EXCEPTIONS=<path>[:<path>...]");
+    println!("  PREFIX_RULES");
+    println!("  KEEP_TERMS");
+    println!("  PROTECTED_TERMS=<path>[:<path>...]");
+    println!("  TERMS_IGNORE_CASE");
+    println!("  TERMS_SUFFIXES");
+    println!("  CHARMAP=<path>");
 }
 
 #[derive(Debug)]
@@ -55,11 +117,11 @@ pub enum Error {
     ArgumentMissing,
     ArgumentUnknown,
     ArgumentInvalid,
-    Runtime(process::Error),
+    Runtime(translitrs::Error),
 }
 
-impl From<process::Error> for Error {
-    fn from(error: process::Error) -> Self {
+impl From<translitrs::Error> for Error {
+    fn from(error: translitrs::Error) -> Self {
         Self::Runtime(error)
     }
 }
@@ -77,23 +139,61 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {}
 
-impl std::str::FromStr for Charset {
-    type Err = Error;
+fn parse_charset(value: &str) -> Result<Charset, Error> {
+    Charset::from_str(value).map_err(|_| Error::ArgumentInvalid)
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "latin" | "lat" | "l" => Ok(Charset::Latin),
-            "latin8" | "lat8" | "l8" => Ok(Charset::LatinUnicode),
-            "cyrillic" | "cyr" | "c" => Ok(Charset::Cyrillic),
-            _ => Err(Error::ArgumentInvalid),
-        }
+fn parse_normalization(value: &str) -> Result<Normalization, Error> {
+    Normalization::from_str(value).map_err(|_| Error::ArgumentInvalid)
+}
+
+/// Returns source charset, or `None` if it has to be detected
+fn parse_charset_from(value: &str) -> Result<Option<Charset>, Error> {
+    match value {
+        "auto" => Ok(None),
+        _ => parse_charset(value).map(Some),
     }
 }
 
+/// Returns ranges of comma separated hexadecimal code points, such as
+/// "00AD,0300-036F"
+fn parse_ignorable(value: &str) -> Result<Vec<RangeInclusive<char>>, Error> {
+    let code_point = |hex: &str| {
+        u32::from_str_radix(hex.trim(), 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(Error::ArgumentInvalid)
+    };
+    value
+        .split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| match item.split_once('-') {
+            Some((first, last)) => Ok(code_point(first)?..=code_point(last)?),
+            None => code_point(item).map(|c| c..=c),
+        })
+        .collect()
+}
+
+fn load_exceptions<P: AsRef<path::Path>>(path: P) -> Result<ExceptionList, Error> {
+    Ok(ExceptionList::load(path).map_err(translitrs::Error::from)?)
+}
+
+fn load_terms<P: AsRef<path::Path>>(path: P) -> Result<ProtectedTerms, Error> {
+    Ok(ProtectedTerms::load(path).map_err(translitrs::Error::from)?)
+}
+
+fn load_charmap(path: &str) -> Result<Charset, Error> {
+    let charmap = Charmap::load(path).map_err(translitrs::Error::from)?;
+    Ok(Charset::Custom(Arc::new(charmap)))
+}
+
 struct Arguments {
     transliterator: Transliterator,
     input: Option<path::PathBuf>,
     output: Option<path::PathBuf>,
+    explain: Option<ExplainFormat>,
+    ambiguous: bool,
+    review: Option<path::PathBuf>,
     #[cfg(feature = "pandoc")]
     pandoc_mode: bool,
 }
@@ -102,11 +202,27 @@ fn parse_args() -> Result<Arguments, Error> {
     let mut input: Option<path::PathBuf> = None;
     let mut output: Option<path::PathBuf> = None;
 
-    let mut charset_from = Charset::Latin;
+    let mut charset_from = Some(Charset::Latin);
     let mut charset_into = Charset::Cyrillic;
+    let mut mixed_scripts = false;
+    let mut repair_homoglyphs = false;
     let mut skip_digraph = false;
+    let mut round_trip = false;
+    let mut ignorable: Option<Vec<RangeInclusive<char>>> = None;
+    let mut normalization = Normalization::Keep;
+    let mut accents = false;
     let mut force_foreign = false;
     let mut force_links = false;
+    let mut foreign_suffixes = false;
+    let mut charmap: Option<Charset> = None;
+    let mut exceptions: Vec<ExceptionList> = Vec::new();
+    let mut prefix_rules = false;
+    let mut protected: Vec<ProtectedTerms> = Vec::new();
+    let mut protected_ignore_case = false;
+    let mut protected_suffixes = false;
+    let mut explain: Option<ExplainFormat> = None;
+    let mut ambiguous = false;
+    let mut review: Option<path::PathBuf> = None;
     #[cfg(feature = "pandoc")]
     let mut pandoc_mode = false;
 
@@ -125,14 +241,14 @@ fn parse_args() -> Result<Arguments, Error> {
             }
             "-t" | "--into" => {
                 if let Some(value) = arguments.next() {
-                    charset_into = Charset::from_str(&value)?
+                    charset_into = parse_charset(&value)?
                 } else {
                     return Err(Error::ArgumentMissing);
                 }
             }
             "-f" | "--from" => {
                 if let Some(value) = arguments.next() {
-                    charset_from = Charset::from_str(&value)?
+                    charset_from = parse_charset_from(&value)?
                 } else {
                     return Err(Error::ArgumentMissing);
                 }
@@ -144,15 +260,99 @@ fn parse_args() -> Result<Arguments, Error> {
                     return Err(Error::ArgumentMissing);
                 }
             }
+            "-m" | "--mixed-scripts" => {
+                mixed_scripts = true;
+            }
+            "-r" | "--repair-homoglyphs" => {
+                repair_homoglyphs = true;
+            }
             "-d" | "--skip-digraph" => {
                 skip_digraph = true;
             }
+            "-z" | "--round-trip" => {
+                round_trip = true;
+            }
+            "-g" | "--ignorable" => {
+                if let Some(value) = arguments.next() {
+                    ignorable = Some(parse_ignorable(&value)?);
+                } else {
+                    return Err(Error::ArgumentMissing);
+                }
+            }
+            "-A" | "--accents" => {
+                accents = true;
+            }
+            "-n" | "--normalize" => {
+                if let Some(value) = arguments.next() {
+                    normalization = parse_normalization(&value)?;
+                } else {
+                    return Err(Error::ArgumentMissing);
+                }
+            }
             "-u" | "--force-foreign" => {
                 force_foreign = true;
             }
+            "-s" | "--suffixes" => {
+                foreign_suffixes = true;
+            }
             "-l" | "--force-links" => {
                 force_links = true;
             }
+            "-e" | "--exceptions" => {
+                if let Some(path) = arguments.next() {
+                    exceptions.push(load_exceptions(path)?);
+                } else {
+                    return Err(Error::ArgumentMissing);
+                }
+            }
+            "-P" | "--prefix-rules" => {
+                prefix_rules = true;
+            }
+            "-C" | "--terms-ignore-case" => {
+                protected_ignore_case = true;
+            }
+            "-S" | "--terms-suffixes" => {
+                protected_suffixes = true;
+            }
+            "-k" | "--keep-terms" => {
+                protected.push(ProtectedTerms::builtin());
+            }
+            "-K" | "--terms" => {
+                if let Some(path) = arguments.next() {
+                    protected.push(load_terms(path)?);
+                } else {
+                    return Err(Error::ArgumentMissing);
+                }
+            }
+            "-c" | "--charmap" => {
+                if let Some(path) = arguments.next() {
+                    charmap = Some(load_charmap(&path)?);
+                } else {
+                    return Err(Error::ArgumentMissing);
+                }
+            }
+            "-x" | "--explain" => {
+                explain = match arguments.next().as_deref() {
+                    Some("text") => Some(ExplainFormat::Text),
+                    Some("json") => Some(ExplainFormat::Json),
+                    Some(_) => return Err(Error::ArgumentInvalid),
+                    None => return Err(Error::ArgumentMissing),
+                };
+            }
+            "-a" | "--ambiguous" => {
+                ambiguous = true;
+            }
+            "-R" | "--review" => {
+                if let Some(path) = arguments.next() {
+                    // Decisions of earlier sessions apply
+                    if path::Path::new(&path).exists() {
+                        exceptions.push(load_exceptions(&path)?);
+                    }
+                    review = Some(path::PathBuf::from(path));
+                } else {
+                    return Err(Error::ArgumentMissing);
+                }
+            }
             #[cfg(feature = "pandoc")]
             "-p" | "--pandoc-filter" => {
                 pandoc_mode = true;
@@ -167,23 +367,207 @@ fn parse_args() -> Result<Arguments, Error> {
             _ => return Err(Error::ArgumentUnknown),
         }
     }
+    if let Some(charmap) = charmap {
+        charset_from = Some(charmap.clone());
+        charset_into = charmap;
+    }
+    let mut builder = Transliterator::builder();
+    for list in exceptions {
+        builder = builder.exceptions(list);
+    }
+    for terms in protected {
+        builder = builder.protected_terms(terms);
+    }
+    if let Some(ignorable) = ignorable {
+        builder = builder.ignorable(ignorable);
+    }
     Ok(Arguments {
-        transliterator: Transliterator::new(
-            charset_from,
-            charset_into,
-            skip_digraph,
-            force_foreign,
-            force_links,
-        ),
+        transliterator: builder
+            .charset_from(charset_from.clone().unwrap_or(Charset::Latin))
+            .detect_from(charset_from.is_none())
+            .charset_into(charset_into)
+            .mixed_scripts(mixed_scripts)
+            .repair_homoglyphs(repair_homoglyphs)
+            .skip_digraph(skip_digraph)
+            .round_trip(round_trip)
+            .normalization(normalization)
+            .accents(accents)
+            .force_foreign(force_foreign)
+            .force_links(force_links)
+            .foreign_suffixes(foreign_suffixes)
+            .prefix_rules(prefix_rules)
+            .protected_ignore_case(protected_ignore_case)
+            .protected_suffixes(protected_suffixes)
+            .build()
+            .map_err(translitrs::Error::from)?,
         input,
         output,
+        explain,
+        ambiguous,
+        review,
         #[cfg(feature = "pandoc")]
         pandoc_mode,
     })
 }
 
+fn confusables_mode(strings: Vec<String>) -> Result<(), Error> {
+    let strings = match strings.is_empty() {
+        true => io::stdin()
+            .lock()
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(translitrs::Error::from)?,
+        false => strings,
+    };
+    let mut mixed = false;
+    for string in strings {
+        let found = find_confusables(&string);
+        if found.is_mixed() {
+            mixed = true;
+            let positions = found
+                .confusables
+                .iter()
+                .map(|c| format!("{}-{}", c.start, c.end))
+                .collect::<Vec<_>>();
+            println!("{}\t{}\t{}", string, found.skeleton, positions.join(","));
+        }
+    }
+    if mixed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn mine_exceptions_mode(paths: Vec<String>) -> Result<(), Error> {
+    let mut corpus = String::new();
+    if paths.is_empty() {
+        io::stdin()
+            .read_to_string(&mut corpus)
+            .map_err(translitrs::Error::from)?;
+    }
+    for path in paths {
+        corpus.push_str(&fs::read_to_string(path).map_err(translitrs::Error::from)?);
+        corpus.push('\n');
+    }
+    let mut group: Option<String> = None;
+    for mined in mine_exceptions(&corpus) {
+        if group.as_ref() != Some(&mined.digraph) {
+            if group.is_some() {
+                println!();
+            }
+            println!("[{}]", mined.digraph);
+            group = Some(mined.digraph);
+        }
+        println!("{}  # {}", mined.exception, mined.count);
+    }
+    Ok(())
+}
+
+fn eval_mode(mut arguments: impl Iterator<Item = String>) -> Result<(), Error> {
+    let mut latin: Option<String> = None;
+    let mut cyrillic: Option<String> = None;
+    let mut exceptions: Vec<ExceptionList> = Vec::new();
+    let mut json = false;
+    while let Some(arg) = arguments.next() {
+        match &*arg {
+            "--latin" => latin = Some(arguments.next().ok_or(Error::ArgumentMissing)?),
+            "--cyrillic" => cyrillic = Some(arguments.next().ok_or(Error::ArgumentMissing)?),
+            "-e" | "--exceptions" => {
+                exceptions.push(load_exceptions(arguments.next().ok_or(Error::ArgumentMissing)?)?)
+            }
+            "--json" => json = true,
+            _ => return Err(Error::ArgumentUnknown),
+        }
+    }
+    let read = |path: Option<String>| -> Result<String, Error> {
+        let path = path.ok_or(Error::ArgumentMissing)?;
+        Ok(fs::read_to_string(path).map_err(translitrs::Error::from)?)
+    };
+    let (latin, cyrillic) = (read(latin)?, read(cyrillic)?);
+    let evaluate = |from: Charset, into: Charset, input: &str, expected: &str| {
+        let mut builder = Transliterator::builder();
+        for list in &exceptions {
+            builder = builder.exceptions(list.clone());
+        }
+        builder
+            .charset_from(from)
+            .charset_into(into)
+            .build()
+            .and_then(|t| t.evaluate(input, expected))
+            .map_err(translitrs::Error::from)
+    };
+    let directions = [
+        (
+            "latin-to-cyrillic",
+            evaluate(Charset::Latin, Charset::Cyrillic, &latin, &cyrillic)?,
+        ),
+        (
+            "cyrillic-to-latin",
+            evaluate(Charset::Cyrillic, Charset::Latin, &cyrillic, &latin)?,
+        ),
+    ];
+    if json {
+        let report = directions
+            .iter()
+            .map(|(name, evaluation)| (*name, evaluation))
+            .collect::<std::collections::BTreeMap<&str, &Evaluation>>();
+        let report = serde_json::to_string_pretty(&report)
+            .map_err(|e| translitrs::Error::from(io::Error::from(e)))?;
+        println!("{}", report);
+        return Ok(());
+    }
+    for (name, evaluation) in &directions {
+        println!(
+            "{}: {}/{} words ({:.2}%)",
+            name,
+            evaluation.correct,
+            evaluation.words,
+            evaluation.accuracy() * 100.0
+        );
+        for cause in [
+            MismatchCause::DigraphException,
+            MismatchCause::ForeignSkip,
+            MismatchCause::LinkSkip,
+            MismatchCause::Case,
+            MismatchCause::Misaligned,
+            MismatchCause::Other,
+        ] {
+            println!("  {}: {}", cause, evaluation.count(cause));
+        }
+        for mismatch in &evaluation.mismatches {
+            println!("{}", mismatch);
+        }
+        println!();
+    }
+    Ok(())
+}
+
 fn regular_mode() -> Result<Box<dyn FileProcessor>, Error> {
     let args = parse_args()?;
+    if let Some(exceptions) = args.review {
+        let input = args.input.ok_or(Error::ArgumentMissing)?;
+        return Ok(Box::new(ReviewProcessor::new(
+            input,
+            args.output,
+            exceptions,
+            args.transliterator,
+        )?));
+    }
+    if args.ambiguous {
+        return Ok(Box::new(AmbiguousProcessor::new(
+            args.input,
+            args.output,
+            args.transliterator,
+        )?));
+    }
+    if let Some(format) = args.explain {
+        return Ok(Box::new(ExplainProcessor::new(
+            args.input,
+            args.output,
+            args.transliterator,
+            format,
+        )?));
+    }
     #[cfg(not(feature = "pandoc"))]
     return Ok(Box::new(PlaintextProcessor::new(args.input, args.output, args.transliterator)?));
     #[cfg(feature = "pandoc")]
@@ -198,7 +582,7 @@ fn pandoc_mode() -> Result<Box<dyn FileProcessor>, Error> {
     fn parse_env_charset(key: &str, default: Charset) -> Result<Charset, Error> {
         if let Ok(value) = env::var(key) {
             if !value.is_empty() {
-                return Charset::from_str(&value);
+                return parse_charset(&value);
             }
         }
         Ok(default)
@@ -209,32 +593,91 @@ fn pandoc_mode() -> Result<Box<dyn FileProcessor>, Error> {
         }
         Ok(default)
     }
-    let transliterator = Transliterator::new(
-        parse_env_charset("CHARS_FROM", Charset::Latin)?,
-        parse_env_charset("CHARS_INTO", Charset::Cyrillic)?,
-        parse_env_bool("SKIP_DIGRAPH", false)?,
-        parse_env_bool("FORCE_FOREIGN", false)?,
-        parse_env_bool("FORCE_LINKS", false)?,
-    );
+    let mut charset_from = match env::var("CHARS_FROM") {
+        Ok(value) if !value.is_empty() => parse_charset_from(&value)?,
+        _ => Some(Charset::Latin),
+    };
+    let mut charset_into = parse_env_charset("CHARS_INTO", Charset::Cyrillic)?;
+    if let Ok(path) = env::var("CHARMAP") {
+        if !path.is_empty() {
+            charset_into = load_charmap(&path)?;
+            charset_from = Some(charset_into.clone());
+        }
+    }
+    let mut builder = Transliterator::builder();
+    if let Some(paths) = env::var_os("DIGRAPH_EXCEPTIONS") {
+        for path in env::split_paths(&paths) {
+            if !path.as_os_str().is_empty() {
+                builder = builder.exceptions(load_exceptions(path)?);
+            }
+        }
+    }
+    if parse_env_bool("KEEP_TERMS", false)? {
+        builder = builder.protected_terms(ProtectedTerms::builtin());
+    }
+    if let Some(paths) = env::var_os("PROTECTED_TERMS") {
+        for path in env::split_paths(&paths) {
+            if !path.as_os_str().is_empty() {
+                builder = builder.protected_terms(load_terms(path)?);
+            }
+        }
+    }
+    if let Ok(value) = env::var("IGNORABLE") {
+        builder = builder.ignorable(parse_ignorable(&value)?);
+    }
+    let transliterator = builder
+        .charset_from(charset_from.clone().unwrap_or(Charset::Latin))
+        .detect_from(charset_from.is_none())
+        .charset_into(charset_into)
+        .mixed_scripts(parse_env_bool("MIXED_SCRIPTS", false)?)
+        .repair_homoglyphs(parse_env_bool("REPAIR_HOMOGLYPHS", false)?)
+        .skip_digraph(parse_env_bool("SKIP_DIGRAPH", false)?)
+        .round_trip(parse_env_bool("ROUND_TRIP", false)?)
+        .accents(parse_env_bool("ACCENTS", false)?)
+        .normalization(match env::var("NORMALIZE") {
+            Ok(value) if !value.is_empty() => parse_normalization(&value)?,
+            _ => Normalization::Keep,
+        })
+        .force_foreign(parse_env_bool("FORCE_FOREIGN", false)?)
+        .force_links(parse_env_bool("FORCE_LINKS", false)?)
+        .foreign_suffixes(parse_env_bool("FOREIGN_SUFFIXES", false)?)
+        .prefix_rules(parse_env_bool("PREFIX_RULES", false)?)
+        .protected_ignore_case(parse_env_bool("TERMS_IGNORE_CASE", false)?)
+        .protected_suffixes(parse_env_bool("TERMS_SUFFIXES", false)?)
+        .build()
+        .map_err(translitrs::Error::from)?;
     Ok(Box::new(PandocProcessor::new(transliterator)))
 }
 
 fn main() -> Result<(), Error> {
+    if env::args().nth(1).as_deref() == Some("confusables") {
+        return confusables_mode(env::args().skip(2).collect());
+    }
+    if env::args().nth(1).as_deref() == Some("eval") {
+        return eval_mode(env::args().skip(2));
+    }
+    if env::args().nth(1).as_deref() == Some("mine-exceptions") {
+        return mine_exceptions_mode(env::args().skip(2).collect());
+    }
     #[cfg(not(feature = "pandoc"))]
-    regular_mode()?.run()?;
+    let mut proc = regular_mode()?;
     #[cfg(feature = "pandoc")]
-    {
-        // Detect if called by Pandoc as a JSON filter
-        let mut proc: Box<dyn FileProcessor> = if let Ok(value) = env::var("PANDOC_VERSION") {
-            if !value.is_empty() {
-                pandoc_mode()
-            } else {
-                regular_mode()
-            }
+    // Detect if called by Pandoc as a JSON filter
+    let mut proc: Box<dyn FileProcessor> = if let Ok(value) = env::var("PANDOC_VERSION") {
+        if !value.is_empty() {
+            pandoc_mode()
         } else {
             regular_mode()
-        }?;
-        proc.run()?;
+        }
+    } else {
+        regular_mode()
+    }?;
+    proc.run()?;
+    if let Some(transliterator) = proc.transliterator() {
+        let repaired = transliterator.repaired_words();
+        if repaired > 0 {
+            eprintln!("Repaired look-alike letters in {} words", repaired);
+        }
     }
     Ok(())
 }
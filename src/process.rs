@@ -1,14 +1,26 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::{error, fmt, io};
 
-use crate::transliterate;
+use crate::transliterate::{self, Transliterator};
 
+mod ambiguous;
+mod explain;
 #[cfg(feature = "pandoc")]
 mod pandoc;
 mod plaintext;
+mod review;
 
 #[cfg(feature = "pandoc")]
 pub use self::pandoc::PandocProcessor;
+pub use ambiguous::AmbiguousProcessor;
+pub use explain::{ExplainFormat, ExplainProcessor};
 pub use plaintext::PlaintextProcessor;
+pub use review::ReviewProcessor;
+
+/// Length of input used to detect source charset
+const DETECTION_SAMPLE: usize = 64 * 1024;
 
 #[derive(Debug)]
 pub enum Error {
@@ -39,6 +51,28 @@ impl From<transliterate::Error> for Error {
     }
 }
 
+/// Opens input file, or standard input if path is not provided
+fn open_input(path: Option<PathBuf>) -> io::Result<Box<dyn Read>> {
+    Ok(match path {
+        Some(p) => Box::from(File::open(p)?),
+        None => Box::from(io::stdin()),
+    })
+}
+
+/// Creates output file, or uses standard output if path is not provided
+fn open_output(path: Option<PathBuf>) -> io::Result<Box<dyn Write>> {
+    Ok(match path {
+        // TODO: Open file later for in-place edits
+        Some(p) => Box::from(File::create(p)?),
+        None => Box::from(io::stdout()),
+    })
+}
+
 pub trait FileProcessor {
     fn run(&mut self) -> Result<(), Error>;
+
+    /// Returns transliterator used by the processor, if there is one
+    fn transliterator(&self) -> Option<&Transliterator> {
+        None
+    }
 }
@@ -0,0 +1,43 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use super::{open_input, open_output, Error, FileProcessor};
+use crate::transliterate::Transliterator;
+
+/// Writes words with digraphs which may have to be kept as separate
+/// letters, one per line, instead of the transliterated text
+pub struct AmbiguousProcessor {
+    input: Box<dyn Read>,
+    output: Box<dyn Write>,
+    processor: Transliterator,
+}
+
+impl AmbiguousProcessor {
+    pub fn new(
+        input: Option<PathBuf>,
+        output: Option<PathBuf>,
+        processor: Transliterator,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            input: open_input(input)?,
+            output: open_output(output)?,
+            processor,
+        })
+    }
+}
+
+impl FileProcessor for AmbiguousProcessor {
+    fn run(&mut self) -> Result<(), Error> {
+        let mut input_string = String::new();
+        self.input.read_to_string(&mut input_string)?;
+        self.processor.detect(&input_string)?;
+        for found in self.processor.ambiguous_digraphs(&input_string) {
+            writeln!(self.output, "{}", found)?;
+        }
+        Ok(())
+    }
+
+    fn transliterator(&self) -> Option<&Transliterator> {
+        Some(&self.processor)
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use super::{open_input, open_output, Error, FileProcessor};
+use crate::transliterate::Transliterator;
+
+/// Format of transliteration report
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExplainFormat {
+    /// One word per line, with its span, action and output separated
+    /// by tabs
+    Text,
+    Json,
+}
+
+/// Writes report of how each word of the input is transliterated,
+/// instead of the transliterated text
+pub struct ExplainProcessor {
+    input: Box<dyn Read>,
+    output: Box<dyn Write>,
+    processor: Transliterator,
+    format: ExplainFormat,
+}
+
+impl ExplainProcessor {
+    pub fn new(
+        input: Option<PathBuf>,
+        output: Option<PathBuf>,
+        processor: Transliterator,
+        format: ExplainFormat,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            input: open_input(input)?,
+            output: open_output(output)?,
+            processor,
+            format,
+        })
+    }
+}
+
+impl FileProcessor for ExplainProcessor {
+    fn run(&mut self) -> Result<(), Error> {
+        let mut input_string = String::new();
+        self.input.read_to_string(&mut input_string)?;
+        self.processor.detect(&input_string)?;
+        let reports = self.processor.explain(&input_string)?;
+        match self.format {
+            ExplainFormat::Text => {
+                for report in reports {
+                    writeln!(self.output, "{}", report)?;
+                }
+            }
+            ExplainFormat::Json => {
+                serde_json::to_writer_pretty(&mut self.output, &reports)
+                    .map_err(io::Error::from)?;
+                writeln!(self.output)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn transliterator(&self) -> Option<&Transliterator> {
+        Some(&self.processor)
+    }
+}
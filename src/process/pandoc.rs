@@ -1,8 +1,9 @@
+use std::borrow::Cow;
 use std::io::{self, Read, Write};
 
 use pandoc_ast::{self, Inline, MutVisitor};
 
-use super::{Error, FileProcessor};
+use super::{Error, FileProcessor, DETECTION_SAMPLE};
 use crate::transliterate::Transliterator;
 
 pub struct PandocProcessor {
@@ -14,7 +15,12 @@ pub struct PandocProcessor {
 impl MutVisitor for PandocProcessor {
     fn visit_inline(&mut self, inline: &mut Inline) {
         if let Inline::Str(ref mut s) = *inline {
-            if let Ok(result) = self.processor.process(&s) {
+            // Replace the string only if transliteration changed it
+            let result = match self.processor.process_cow(s) {
+                Ok(Cow::Owned(result)) => Some(result),
+                _ => None,
+            };
+            if let Some(result) = result {
                 *s = result;
             }
         }
@@ -22,6 +28,22 @@ impl MutVisitor for PandocProcessor {
     }
 }
 
+/// Text of the document used to detect source charset
+struct Sample(String);
+
+impl MutVisitor for Sample {
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        if self.0.len() >= DETECTION_SAMPLE {
+            return;
+        }
+        if let Inline::Str(ref s) = *inline {
+            self.0.push_str(s);
+            self.0.push(' ');
+        }
+        self.walk_inline(inline);
+    }
+}
+
 impl PandocProcessor {
     pub fn new(processor: Transliterator) -> Self {
         Self {
@@ -36,11 +58,23 @@ impl FileProcessor for PandocProcessor {
     fn run(&mut self) -> Result<(), Error> {
         let mut input_string = String::new();
         self.input.read_to_string(&mut input_string)?;
+        let mut result = Ok(None);
         let output_string = pandoc_ast::filter(input_string, |mut pandoc| {
-            self.walk_pandoc(&mut pandoc);
+            if self.processor.detects_from() {
+                let mut sample = Sample(String::new());
+                sample.walk_pandoc(&mut pandoc);
+                result = self.processor.detect(&sample.0);
+            }
+            if result.is_ok() {
+                self.walk_pandoc(&mut pandoc);
+            }
             pandoc
         });
+        result?;
         self.output.write_all(output_string.as_bytes())?;
         Ok(())
     }
+    fn transliterator(&self) -> Option<&Transliterator> {
+        Some(&self.processor)
+    }
 }
@@ -1,9 +1,8 @@
-use std::fs::File;
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
 
-use super::{Error, FileProcessor};
-use crate::transliterate::Transliterator;
+use super::{open_input, open_output, Error, FileProcessor, DETECTION_SAMPLE};
+use crate::transliterate::{TransliteratingWriter, Transliterator};
 
 pub struct PlaintextProcessor {
     input: Box<dyn Read>,
@@ -18,17 +17,8 @@ impl PlaintextProcessor {
         processor: Transliterator,
     ) -> Result<Self, Error> {
         Ok(Self {
-            input: if let Some(p) = input {
-                Box::from(File::open(p)?)
-            } else {
-                Box::from(io::stdin())
-            },
-            output: if let Some(p) = output {
-                // TODO: Open file later for in-place edits
-                Box::from(File::create(p)?)
-            } else {
-                Box::from(io::stdout())
-            },
+            input: open_input(input)?,
+            output: open_output(output)?,
             processor,
         })
     }
@@ -36,10 +26,20 @@ impl PlaintextProcessor {
 
 impl FileProcessor for PlaintextProcessor {
     fn run(&mut self) -> Result<(), Error> {
-        let mut input_string = String::new();
-        self.input.read_to_string(&mut input_string)?;
-        let output_string = self.processor.process(input_string)?;
-        self.output.write_all(output_string.as_bytes())?;
+        // Detect source charset from the beginning of input
+        let mut sample = Vec::new();
+        if self.processor.detects_from() {
+            (&mut self.input)
+                .take(DETECTION_SAMPLE as u64)
+                .read_to_end(&mut sample)?;
+            self.processor.detect(&String::from_utf8_lossy(&sample))?;
+        }
+        let mut writer = TransliteratingWriter::new(&mut self.output, &self.processor);
+        io::copy(&mut sample.as_slice().chain(&mut self.input), &mut writer)?;
+        writer.finish()?;
         Ok(())
     }
+    fn transliterator(&self) -> Option<&Transliterator> {
+        Some(&self.processor)
+    }
 }
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+use super::{open_output, Error, FileProcessor};
+use crate::transliterate::{AmbiguousDigraph, Transliterator};
+
+/// Reviewer's choice for a word form
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Decision {
+    /// Letters of the digraph are kept separate
+    Split,
+    /// Digraph is transliterated as a single letter
+    Digraph,
+    /// Word is transliterated as usual and no decision is recorded
+    Skip,
+}
+
+/// Asks the reviewer how to read each ambiguous digraph of the input,
+/// appending decisions to an exception list and writing the
+/// transliterated text with decisions applied
+///
+/// Questions are written to standard error and answers are read from
+/// standard input, so the input has to be read from a file.
+pub struct ReviewProcessor {
+    input: Box<dyn Read>,
+    output: Box<dyn Write>,
+    answers: Box<dyn BufRead>,
+    prompt: Box<dyn Write>,
+    exceptions: PathBuf,
+    processor: Transliterator,
+}
+
+impl ReviewProcessor {
+    pub fn new(
+        input: PathBuf,
+        output: Option<PathBuf>,
+        exceptions: PathBuf,
+        processor: Transliterator,
+    ) -> Result<Self, Error> {
+        Ok(Self::from_streams(
+            File::open(input)?,
+            open_output(output)?,
+            BufReader::new(io::stdin()),
+            io::stderr(),
+            exceptions,
+            processor,
+        ))
+    }
+
+    /// Returns processor reading answers from `answers` and writing
+    /// questions to `prompt`, instead of standard input and error
+    pub fn from_streams(
+        input: impl Read + 'static,
+        output: impl Write + 'static,
+        answers: impl BufRead + 'static,
+        prompt: impl Write + 'static,
+        exceptions: PathBuf,
+        processor: Transliterator,
+    ) -> Self {
+        Self {
+            input: Box::new(input),
+            output: Box::new(output),
+            answers: Box::new(answers),
+            prompt: Box::new(prompt),
+            exceptions,
+            processor,
+        }
+    }
+
+    /// Transliterates word keeping letters of the digraph separate
+    fn split_reading(&self, found: &AmbiguousDigraph) -> Result<String, Error> {
+        let (before, after) = found.word.split_at(found.split);
+        Ok(self.processor.process_word(before)? + &self.processor.process_word(after)?)
+    }
+
+    /// Asks for a decision, returning `None` if the reviewer quits
+    fn ask(&mut self, found: &AmbiguousDigraph, split: &str) -> Result<Option<Decision>, Error> {
+        let digraph = self.processor.process_word(&found.word)?;
+        writeln!(self.prompt, "\n{}: {}", found.line, found.context)?;
+        loop {
+            write!(
+                self.prompt,
+                "{}: [1] {}  [2] {}  [s]kip  [q]uit? ",
+                found.word, split, digraph
+            )?;
+            self.prompt.flush()?;
+            let mut answer = String::new();
+            if self.answers.read_line(&mut answer)? == 0 {
+                writeln!(self.prompt)?;
+                return Ok(None);
+            }
+            match answer.trim() {
+                "1" => return Ok(Some(Decision::Split)),
+                "2" => return Ok(Some(Decision::Digraph)),
+                "s" | "" => return Ok(Some(Decision::Skip)),
+                "q" => return Ok(None),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Appends decision to exception list
+    fn record(&self, found: &AmbiguousDigraph, decision: Decision) -> Result<(), Error> {
+        let entry = match decision {
+            Decision::Split => found.exception(),
+            Decision::Digraph => format!("!{}", found.exception().replace('|', "")),
+            Decision::Skip => return Ok(()),
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.exceptions)?;
+        writeln!(file, "[{}]\n{}", found.digraph, entry)?;
+        Ok(())
+    }
+}
+
+impl FileProcessor for ReviewProcessor {
+    fn run(&mut self) -> Result<(), Error> {
+        let mut input_string = String::new();
+        self.input.read_to_string(&mut input_string)?;
+        self.processor.detect(&input_string)?;
+        // Decision for each word form, so it is asked once
+        let mut decisions: HashMap<String, Decision> = HashMap::new();
+        let mut quit = false;
+        let mut output = String::with_capacity(input_string.len());
+        let mut cursor = 0;
+        for found in self.processor.ambiguous_digraphs(&input_string) {
+            let split = self.split_reading(&found)?;
+            let form = found.exception();
+            let decision = match decisions.get(&form) {
+                Some(decision) => *decision,
+                None if quit => Decision::Skip,
+                None => match self.ask(&found, &split)? {
+                    Some(decision) => {
+                        self.record(&found, decision)?;
+                        decisions.insert(form, decision);
+                        decision
+                    }
+                    None => {
+                        quit = true;
+                        Decision::Skip
+                    }
+                },
+            };
+            self.processor
+                .process_into(&input_string[cursor..found.start], &mut output)?;
+            match decision {
+                Decision::Split => output.push_str(&split),
+                _ => output.push_str(&self.processor.process_word(&found.word)?),
+            }
+            cursor = found.end;
+        }
+        self.processor
+            .process_into(&input_string[cursor..], &mut output)?;
+        self.output.write_all(output.as_bytes())?;
+        Ok(())
+    }
+
+    fn transliterator(&self) -> Option<&Transliterator> {
+        Some(&self.processor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::fs;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Writer which can be read after the processor is done with it
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    /// Runs review of `input` with scripted `answers`, returning output,
+    /// prompt and recorded exceptions
+    fn review(name: &str, input: &'static str, answers: &'static str) -> (String, String, String) {
+        let exceptions = std::env::temp_dir().join(format!(
+            "translitrs-review-{}-{}.txt",
+            name,
+            std::process::id()
+        ));
+        let (output, prompt) = (Buffer::default(), Buffer::default());
+        let mut processor = ReviewProcessor::from_streams(
+            input.as_bytes(),
+            output.clone(),
+            answers.as_bytes(),
+            prompt.clone(),
+            exceptions.clone(),
+            Transliterator::default(),
+        );
+        processor.run().unwrap();
+        let recorded = fs::read_to_string(&exceptions).unwrap_or_default();
+        let _ = fs::remove_file(&exceptions);
+        (output.text(), prompt.text(), recorded)
+    }
+
+    #[test]
+    fn test_review() {
+        let input = "Injunkcija i injunkcija.\nInjunkciju, injunkcije!";
+        // Invalid answer is asked again, and quitting skips the rest
+        let (output, prompt, recorded) = review("decisions", input, "x\n1\n2\nq\n");
+        assert_eq!(output, "Инјункција и инјункција.\nИњункцију, ињункције!");
+        assert_eq!(prompt.matches("[q]uit?").count(), 4);
+        // Second occurrence of a word form on the first line isn't asked
+        assert_eq!(prompt.matches("\n1: ").count(), 1);
+        assert_eq!(recorded, "[nj]\nin|junkcija\n[nj]\n!injunkciju\n");
+        // End of answers skips all words without recording them
+        let (output, prompt, recorded) = review("eof", input, "");
+        assert_eq!(output, "Ињункција и ињункција.\nИњункцију, ињункције!");
+        assert_eq!(prompt.matches("[q]uit?").count(), 1);
+        assert_eq!(recorded, "");
+    }
+}
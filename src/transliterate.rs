@@ -1,35 +1,128 @@
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::{cmp, error, fmt, str, string};
-use subslice::bmh;
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{cmp, error, fmt, io, mem, str, string};
+use unicode_normalization::UnicodeNormalization;
 
+mod align;
+mod ambiguous;
+mod builder;
 mod charmaps;
+mod confusables;
+mod custom;
+mod detect;
+mod eval;
+mod exceptions;
+mod explain;
+mod homoglyph;
+mod mine;
+mod normalize;
+mod protected;
+mod stream;
+mod trie;
 
+pub use align::{Alignment, Span};
+pub use ambiguous::AmbiguousDigraph;
+pub use builder::TransliteratorBuilder;
 use charmaps::{Case as LetterCase, Character};
+pub use confusables::{find_confusables, Confusable, Confusables};
+pub use custom::Charmap;
+pub use detect::{detect_charset, Detection};
+pub use eval::{Evaluation, Mismatch, MismatchCause};
+pub use exceptions::{ExceptionList, ExceptionMatch};
+use exceptions::{ExceptionMatcher, WordExceptions};
+pub use explain::{WordAction, WordReport};
+pub use mine::{mine_exceptions, MinedException};
+pub use normalize::Normalization;
+pub use protected::ProtectedTerms;
+use protected::TermMatcher;
+pub use stream::{TransliteratingReader, TransliteratingWriter};
+use trie::CharmapTrie;
 
-#[derive(Clone)]
+lazy_static! {
+    // Borrowed from https://stackoverflow.com/a/26093611
+    static ref RE_DOMAIN: Regex = Regex::new(
+        r"[a-zA-Z0-9][a-zA-Z0-9-]{1,61}[a-zA-Z0-9](?:\.[a-zA-Z]{2,})+"
+    ).unwrap();
+    // Borrowed from https://www.emailregex.com/
+    static ref RE_EMAIL: Regex =
+        Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,6}\b").unwrap();
+    // Borrowed from http://urlregex.com/
+    static ref RE_URL: Regex = Regex::new(
+        r"((http[s]?://)|(./)|(/))(?:[a-zA-Z]|[0-9]|[$-_@.&+]|[!*\(\),]|(?:%[0-9a-fA-F][0-9a-fA-F]))+"
+    ).unwrap();
+    // Borrowed from https://github.com/turanjanin/cirilizator
+    static ref RE_MEASUREMENT: Regex = Regex::new(
+        r"(\d+([\.,]\d)*)((K|°[FC]|[kKMGTPEY](ib|b|iB|B|Hz)|[pnμmcdhk]m[²³]?|m[²³]|[mcdkh][lg])|([zafpnμmcdhKMGTPEY]?([BVWJFSHCΩATNhlmg]|m[²³]|s[²]|cd|Pa|Wb|Hz|deg|rad)))"
+    ).unwrap();
+}
+
+#[derive(Clone, Debug)]
 pub enum Charset {
     Latin,
     LatinUnicode,
     Cyrillic,
+    /// Runtime-loaded charmap, which has to be used both as source and
+    /// target charset
+    Custom(Arc<Charmap>),
 }
 
 pub struct Transliterator {
-    charset_from: &'static [Character<'static>],
-    charset_into: &'static [Character<'static>],
+    charset_from: Cow<'static, [Character<'static>]>,
+    charset_into: Cow<'static, [Character<'static>]>,
+    matcher: CharmapTrie,
+    exception_matcher: ExceptionMatcher,
     exceptions: bool,
+    protected: TermMatcher,
     skip_digraph: bool,
     force_foreign: bool,
     force_links: bool,
+    repair_homoglyphs: bool,
+    round_trip: bool,
+    // Letter pairs of target charset read as a single letter when
+    // transliterating back
+    joined: Vec<[char; 2]>,
+    // Letter pairs of source charset read as a single letter
+    digraphs: Vec<[char; 2]>,
+    // Characters which are kept in place but skipped when matching
+    ignorable: Vec<RangeInclusive<char>>,
+    normalization: Normalization,
+    accents: bool,
+    foreign_suffixes: bool,
+    // Number of words with repaired look-alike letters
+    repaired: AtomicUsize,
+    // Transliterators for each source script in mixed-script mode
+    scripts: Vec<(Charset, Transliterator)>,
+    // Options to rebuild with once source charset is detected
+    options: Option<TransliteratorBuilder>,
 }
 
 #[derive(Debug)]
 pub enum Error {
     BufferOverflow,
+    Charmap(usize, String),
+    CharmapMismatch(usize, usize),
+    ExceptionList(usize, String),
+    InvalidException(String, String),
+    ProtectedTerms(usize, String),
+    UnknownExceptionGroup(String),
+    UnpairedCharmap,
+    UnknownCharset(String),
+    UnknownNormalization(String),
+    Io(io::Error),
     Utf8(str::Utf8Error),
     FromUtf8(string::FromUtf8Error),
 }
 
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
 impl From<str::Utf8Error> for Error {
     fn from(error: str::Utf8Error) -> Self {
         Self::Utf8(error)
@@ -46,6 +139,30 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::BufferOverflow => writeln!(f, "Buffer Overflow"),
+            Self::Charmap(line, e) => writeln!(f, "Invalid charmap on line {} - {}", line, e),
+            Self::ExceptionList(line, e) => {
+                writeln!(f, "Invalid exception list on line {} - {}", line, e)
+            }
+            Self::InvalidException(exception, e) => {
+                writeln!(f, "Invalid digraph exception \"{}\" - {}", exception, e)
+            }
+            Self::ProtectedTerms(line, e) => {
+                writeln!(f, "Invalid protected terms on line {} - {}", line, e)
+            }
+            Self::UnknownExceptionGroup(name) => {
+                writeln!(f, "Unknown digraph exception group - {}", name)
+            }
+            Self::UnpairedCharmap => {
+                writeln!(f, "Custom charmap has to be both source and target charset")
+            }
+            Self::Io(e) => writeln!(f, "IO error - {}", e),
+            Self::CharmapMismatch(from, into) => writeln!(
+                f,
+                "Charmap length mismatch - {} source and {} target characters",
+                from, into
+            ),
+            Self::UnknownCharset(s) => writeln!(f, "Unknown character set - {}", s),
+            Self::UnknownNormalization(s) => writeln!(f, "Unknown normal form - {}", s),
             Self::Utf8(e) => writeln!(f, "UTF-8 error - {}", e),
             Self::FromUtf8(e) => writeln!(f, "From UTF-8 error - {}", e),
         }
@@ -54,22 +171,54 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {}
 
+impl PartialEq for Charset {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            (a, b) => mem::discriminant(a) == mem::discriminant(b),
+        }
+    }
+}
+
+impl str::FromStr for Charset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latin" | "lat" | "l" => Ok(Charset::Latin),
+            "latin8" | "lat8" | "l8" => Ok(Charset::LatinUnicode),
+            "cyrillic" | "cyr" | "c" => Ok(Charset::Cyrillic),
+            _ => Err(Error::UnknownCharset(s.to_owned())),
+        }
+    }
+}
+
+/// Returns length of characters encoded as UTF-8
+fn utf8_len(chars: &[char]) -> usize {
+    chars.iter().map(|c| c.len_utf8()).sum()
+}
+
 impl Default for Transliterator {
     fn default() -> Self {
-        Self {
-            charset_from: charmaps::LATIN_DIRTY,
-            charset_into: charmaps::CYRILLIC_DIRTY,
-            exceptions: true,
-            skip_digraph: false,
-            force_foreign: false,
-            force_links: false,
-        }
+        TransliteratorBuilder::default()
+            .build()
+            .expect("built-in charmaps should be valid")
     }
 }
 
+/// Returns first two letters of each charset entry with several letters
+fn digraphs(charset: &[Character]) -> Vec<[char; 2]> {
+    charset
+        .iter()
+        .filter(|l| l.value.len() > 1)
+        .map(|l| [l.value[0], l.value[1]])
+        .collect()
+}
+
 #[allow(unused)]
 impl Transliterator {
-    /// Returns string transliterator
+    /// Returns string transliterator. Prefer [`Transliterator::builder`],
+    /// which names each option.
     ///
     /// # Arguments
     ///
@@ -79,6 +228,12 @@ impl Transliterator {
     /// * `force_foreign` - Force transliterate foreign words (ie. words that contain characters not found in `from` charset)
     /// * `force_links` - Force transliterate URLs
     ///
+    /// # Panics
+    ///
+    /// Panics if either charset is [`Charset::Custom`], since custom
+    /// charmaps may fail to pair. Use [`TransliteratorBuilder::build`]
+    /// for them instead.
+    ///
     /// # Example
     ///
     /// ```
@@ -95,110 +250,582 @@ impl Transliterator {
         force_foreign: bool,
         force_links: bool,
     ) -> Self {
-        let (f, i, e) = match (from, into) {
-            (Charset::Latin, Charset::Latin) => (charmaps::EMPTY, charmaps::EMPTY, false),
-            (Charset::LatinUnicode, Charset::LatinUnicode) => {
-                (charmaps::EMPTY, charmaps::EMPTY, false)
-            }
-            (Charset::Cyrillic, Charset::Cyrillic) => (charmaps::EMPTY, charmaps::EMPTY, false),
-            //
-            (Charset::Latin, Charset::LatinUnicode) => {
-                (charmaps::LATIN_DIRTY, charmaps::LATIN_DIRTY_UNICODE, true)
-            }
-            (Charset::LatinUnicode, Charset::Latin) => {
-                (charmaps::LATIN_CLEAN_UNICODE, charmaps::LATIN_CLEAN, false)
-            }
-            //
-            (Charset::Latin, Charset::Cyrillic) => {
-                (charmaps::LATIN_DIRTY, charmaps::CYRILLIC_DIRTY, true)
+        if matches!(from, Charset::Custom(_)) || matches!(into, Charset::Custom(_)) {
+            panic!("custom charmaps have to be built with TransliteratorBuilder::build");
+        }
+        TransliteratorBuilder::new()
+            .charset_from(from)
+            .charset_into(into)
+            .skip_digraph(skip_digraph)
+            .force_foreign(force_foreign)
+            .force_links(force_links)
+            .build()
+            .expect("built-in charmaps should be valid")
+    }
+
+    /// Returns builder of a transliterator with named options
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use translitrs::{Charset, Transliterator};
+    ///
+    /// let t = Transliterator::builder()
+    ///     .charset_into(Charset::LatinUnicode)
+    ///     .skip_digraph(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(t.process("Konjugacija").unwrap(), "Koǌugacija");
+    /// ```
+    pub fn builder() -> TransliteratorBuilder {
+        TransliteratorBuilder::new()
+    }
+
+    /// Checks if source charset has to be detected with
+    /// [`Transliterator::detect`]
+    pub fn detects_from(&self) -> bool {
+        self.options.is_some()
+    }
+
+    /// Returns number of words in which letters that look like letters
+    /// of the other script have been repaired
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use translitrs::Transliterator;
+    ///
+    /// let t = Transliterator::builder().repair_homoglyphs(true).build().unwrap();
+    /// // Word contains Latin "a" and "o"
+    /// assert_eq!(t.process("Јaвoр и javor").unwrap(), "Јавор и јавор");
+    /// assert_eq!(t.repaired_words(), 1);
+    /// ```
+    pub fn repaired_words(&self) -> usize {
+        self.repaired.load(Ordering::Relaxed)
+    }
+
+    /// Detects source charset from a sample of text and rebuilds the
+    /// transliterator for it. Does nothing and returns `None` unless
+    /// detection is enabled with [`TransliteratorBuilder::detect_from`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use translitrs::{Charset, Transliterator};
+    ///
+    /// let mut t = Transliterator::builder()
+    ///     .charset_into(Charset::Latin)
+    ///     .detect_from(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let detection = t.detect("Ђурђевак").unwrap().unwrap();
+    /// assert!(matches!(detection.charset, Charset::Cyrillic));
+    /// assert_eq!(t.process("Ђурђевак").unwrap(), "Đurđevak");
+    /// ```
+    pub fn detect(&mut self, sample: &str) -> Result<Option<Detection>, Error> {
+        let options = match &self.options {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+        let detection = detect_charset(sample);
+        *self = options
+            .clone()
+            .charset_from(detection.charset.clone())
+            .build()?;
+        Ok(Some(detection))
+    }
+
+    /// Returns replacement for a Latin digraph at character `position`
+    /// and the matching exception string if the word matches one of its
+    /// exceptions. Word is searched for exceptions only once, the first
+    /// time a digraph is found.
+    fn digraph_exception<'a>(
+        &'a self,
+        word: &[char],
+        cache: &mut Option<WordExceptions<'a>>,
+        character: &[char],
+        position: usize,
+    ) -> Option<(&'a Character<'static>, &'a str)> {
+        let (group, i) = self.exception_matcher.group(character)?;
+        let found = cache.get_or_insert_with(|| self.exception_matcher.find(word));
+        let exception = found.at(group, position)?.exception;
+        Some((&self.exception_matcher.groups()[group].target[i], exception))
+    }
+
+    /// Returns all digraph exception strings found within a word, ignoring
+    /// case. Positions refer to characters of the word.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use translitrs::{Charset, Transliterator};
+    ///
+    /// let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+    /// let found = t.digraph_exceptions("Injekcija");
+    /// assert_eq!(found[0].exception, "injekc");
+    /// assert_eq!((found[0].start, found[0].end), (0, 6));
+    /// ```
+    pub fn digraph_exceptions(&self, word: &str) -> Vec<ExceptionMatch<'_>> {
+        let chars = word.chars().collect::<Vec<char>>();
+        self.exception_matcher.find(&chars).matches()
+    }
+
+    /// Checks if a word contains a domain or an email address
+    fn address_pattern(word: &str) -> bool {
+        RE_DOMAIN.is_match(word) || RE_EMAIL.is_match(word)
+    }
+
+    fn foreign_pattern_exception(word: &str) -> bool {
+        Self::address_pattern(word) || RE_URL.is_match(word) || RE_MEASUREMENT.is_match(word)
+    }
+
+    /// Checks if character is skipped when matching letters
+    fn ignores(&self, c: char) -> bool {
+        (self.accents && charmaps::ACCENTS.contains(&c))
+            || self.ignorable.iter().any(|r| r.contains(&c))
+    }
+
+    /// Transliterates word without its ignorable characters, then puts
+    /// each of them back after the output of the unit it follows or is
+    /// placed within
+    fn transliterate_ignoring(
+        &self,
+        word: &str,
+        output: &mut String,
+        report: Option<&mut WordReport>,
+    ) -> Result<bool, Error> {
+        let mut stripped = String::with_capacity(word.len());
+        // Ignorable characters with their byte index in the word and in
+        // the stripped word
+        let mut ignored = Vec::new();
+        for (i, c) in word.char_indices() {
+            match self.ignores(c) {
+                true => ignored.push((i, stripped.len(), c)),
+                false => stripped.push(c),
             }
-            (Charset::LatinUnicode, Charset::Cyrillic) => {
-                (charmaps::LATIN_CLEAN_UNICODE, charmaps::CYRILLIC_CLEAN, true)
+        }
+        let mut inner = WordReport::new(0, &stripped);
+        let start = output.len();
+        let converted = self.convert_word(&stripped, output, Some(&mut inner))?;
+        let report = match report {
+            Some(report) => {
+                report.action = inner.action;
+                report.repaired = inner.repaired;
+                Some(report)
             }
-            //
-            (Charset::Cyrillic, Charset::Latin) => {
-                (charmaps::CYRILLIC_CLEAN, charmaps::LATIN_CLEAN, false)
+            None => None,
+        };
+        if !converted {
+            return Ok(false);
+        }
+        let stripped_output = output.split_off(start);
+        let mut units = Vec::with_capacity(inner.units.len() + ignored.len());
+        let mut ignored = ignored.into_iter().peekable();
+        // Length of ignorable characters before the current unit
+        let mut shift = 0;
+        for unit in inner.units {
+            while let Some((i, _, c)) = ignored.next_if(|&(_, at, _)| at <= unit.input.start) {
+                let into = output.len() - start;
+                output.push(c);
+                units.push(Span {
+                    input: i..i + c.len_utf8(),
+                    output: into..output.len() - start,
+                });
+                shift += c.len_utf8();
             }
-            (Charset::Cyrillic, Charset::LatinUnicode) => {
-                (charmaps::CYRILLIC_CLEAN, charmaps::LATIN_CLEAN_UNICODE, false)
+            let from = unit.input.start + shift;
+            let into = output.len() - start;
+            output.push_str(&stripped_output[unit.output]);
+            while let Some((_, _, c)) = ignored.next_if(|&(_, at, _)| at < unit.input.end) {
+                output.push(c);
+                shift += c.len_utf8();
             }
+            units.push(Span {
+                input: from..unit.input.end + shift,
+                output: into..output.len() - start,
+            });
+        }
+        for (i, _, c) in ignored {
+            let into = output.len() - start;
+            output.push(c);
+            units.push(Span {
+                input: i..i + c.len_utf8(),
+                output: into..output.len() - start,
+            });
+        }
+        if let Some(report) = report {
+            report.units = units;
+        }
+        Ok(true)
+    }
+
+    /// Checks if character starts an entry of source charset
+    fn starts_letter(&self, c: char) -> bool {
+        self.charset_from.iter().any(|l| l.value[0] == c)
+    }
+
+    /// Transliterates word with its letters normalized, if any of them
+    /// are accepted. Returns `None` if the word is left unchanged.
+    fn transliterate_normalized(
+        &self,
+        word: &str,
+        form: Normalization,
+        accept: impl Fn(&str) -> bool,
+        output: &mut String,
+        report: Option<&mut WordReport>,
+    ) -> Result<Option<bool>, Error> {
+        let (normalized, runs) = match normalize::normalize(word, form, accept) {
+            Some(normalized) => normalized,
+            None => return Ok(None),
         };
-        assert_eq!(f.len(), i.len());
-        Self {
-            charset_from: f,
-            charset_into: i,
-            exceptions: e,
-            skip_digraph,
-            force_foreign,
-            force_links,
+        let mut inner = WordReport::new(0, &normalized);
+        let converted = self.convert_word(&normalized, output, Some(&mut inner))?;
+        if let Some(report) = report {
+            report.action = inner.action;
+            report.repaired = inner.repaired;
+            report.units = runs.reversed().remap(inner.units, true);
         }
+        Ok(Some(converted))
     }
 
-    fn chars_to_utf8(input: &[char], output: &mut [u8]) -> Result<usize, Error> {
-        let mut cursor: usize = 0;
-        for c in input {
-            let length = c.len_utf8();
-            if cursor + length > output.len() {
-                return Err(Error::BufferOverflow);
-            }
-            c.encode_utf8(&mut output[cursor..cursor + length]);
-            cursor += length;
+    /// Keeps root of a word followed by a hyphen or an apostrophe and a
+    /// case suffix (eg. Facebook-u) as is, transliterating the suffix.
+    /// Returns `None` if the word has no such suffix or its root is
+    /// written in Cyrillic.
+    fn transliterate_suffix(
+        &self,
+        word: &str,
+        output: &mut String,
+        report: Option<&mut WordReport>,
+    ) -> Result<Option<bool>, Error> {
+        let end = word.trim_end_matches(|c: char| !c.is_alphanumeric()).len();
+        let (i, separator) = match word[..end]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| matches!(c, '-' | '\'' | '’'))
+        {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let suffix = i + separator.len_utf8();
+        if i == 0 || !charmaps::CASE_SUFFIXES.contains(&word[suffix..end].to_lowercase().as_str()) {
+            return Ok(None);
+        }
+        // Only foreign names take a hyphenated ending, even if written
+        // with Serbian Latin letters, while Cyrillic roots are already
+        // transcribed and are converted whole
+        if word[..i]
+            .chars()
+            .any(|c| homoglyph::is_cyrillic(c) == Some(true))
+        {
+            return Ok(None);
         }
-        Ok(cursor)
+        let start = output.len();
+        output.push_str(&word[..suffix]);
+        let mut inner = WordReport::new(0, &word[suffix..]);
+        if !self.convert_word(&word[suffix..], output, Some(&mut inner))? {
+            output.truncate(start);
+            return Ok(None);
+        }
+        if let Some(report) = report {
+            report.action = WordAction::SuffixConverted {
+                root: word[..i].to_owned(),
+            };
+            report.units = Span::identity(&word[..suffix], 0, 0);
+            report.units.extend(inner.units.into_iter().map(|s| Span {
+                input: suffix + s.input.start..suffix + s.input.end,
+                output: suffix + s.output.start..suffix + s.output.end,
+            }));
+        }
+        Ok(Some(true))
     }
 
-    fn digraph_exception<'a>(
-        word: &[char],
-        character: &'a [char],
-        latinize: bool,
-    ) -> Result<Option<&'a Character<'a>>, Error> {
-        for exception in charmaps::DIGRAPH_EXCEPTIONS {
-            for i in 0..exception.latin.len() {
-                if exception.latin[i].value == character {
-                    let mut lowercase: Vec<u8> = vec![0; word.len() * 4];
-                    let mut cursor: usize = 0;
-                    for letter in word {
-                        for c in letter.to_lowercase() {
-                            cursor += Self::chars_to_utf8(&[c], &mut lowercase[cursor..])?;
-                        }
+    /// Appends transliterated word to `output`, normalized to the
+    /// selected form. Returns `false` and leaves `output` untouched if
+    /// the word has to be kept as is and is already normalized.
+    fn transliterate_word(
+        &self,
+        word: &str,
+        output: &mut String,
+        mut report: Option<&mut WordReport>,
+    ) -> Result<bool, Error> {
+        if self.normalization == Normalization::Keep {
+            return self.convert_word(word, output, report);
+        }
+        let start = output.len();
+        let converted = self.convert_word(word, output, report.as_deref_mut())?;
+        // Skipped links are kept byte for byte
+        if !converted && !self.force_links && Self::foreign_pattern_exception(word) {
+            return Ok(false);
+        }
+        let text = match converted {
+            true => &output[start..],
+            false => word,
+        };
+        let (normalized, runs) = match normalize::normalize(text, self.normalization, |_| true) {
+            Some(normalized) => normalized,
+            None => return Ok(converted),
+        };
+        output.truncate(start);
+        output.push_str(&normalized);
+        if let Some(report) = report {
+            let units = match converted {
+                true => mem::take(&mut report.units),
+                false => Span::identity(word, 0, 0),
+            };
+            report.units = runs.remap(units, false);
+        }
+        Ok(true)
+    }
+
+    /// Appends transliterated word to `output`. Returns `false` and
+    /// leaves `output` untouched if the word has to be kept as is.
+    /// Actions taken are recorded into `report`, if it is provided.
+    fn convert_word(
+        &self,
+        word: &str,
+        output: &mut String,
+        mut report: Option<&mut WordReport>,
+    ) -> Result<bool, Error> {
+        // Decomposed letters are matched as precomposed charset entries,
+        // such as "c" followed by a caron read as "č"
+        let letter = |run: &str| run.chars().next().map_or(false, |c| self.starts_letter(c));
+        if let Some(converted) = self.transliterate_normalized(
+            word,
+            Normalization::Nfc,
+            letter,
+            output,
+            report.as_deref_mut(),
+        )? {
+            return Ok(converted);
+        }
+        // Accented vowels are read as their base letter followed by the
+        // accent, which is carried over as an ignorable character
+        if self.accents {
+            let accented = |run: &str| {
+                let mut chars = run.chars();
+                chars
+                    .next()
+                    .map_or(false, |c| charmaps::ACCENTED.contains(&c))
+                    && chars.all(|c| charmaps::ACCENTS.contains(&c))
+                    // Accented letters found in source charset are kept
+                    && !letter(&run.nfc().collect::<String>())
+            };
+            if let Some(converted) = self.transliterate_normalized(
+                word,
+                Normalization::Nfd,
+                accented,
+                output,
+                report.as_deref_mut(),
+            )? {
+                return Ok(converted);
+            }
+        }
+        // Ignorable characters are put back once the rest is transliterated
+        if word.chars().any(|c| self.ignores(c)) {
+            return self.transliterate_ignoring(word, output, report);
+        }
+        // Links are kept as written, so spoofed letters stay visible
+        if self.repair_homoglyphs && (self.force_links || !Self::foreign_pattern_exception(word)) {
+            if let Some(repaired) = homoglyph::repair(word) {
+                self.repaired.fetch_add(1, Ordering::Relaxed);
+                // Repaired word is kept even if it isn't transliterated
+                let converted = self.convert_word(&repaired, output, report.as_deref_mut())?;
+                if !converted {
+                    output.push_str(&repaired);
+                }
+                if let Some(report) = report {
+                    if !converted {
+                        report.units = Span::identity(&repaired, 0, 0);
+                    }
+                    // Letters are replaced one by one, but their lengths differ
+                    let from = repaired.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+                    let into = word.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+                    let remap = |i: usize| match from.binary_search(&i) {
+                        Ok(c) => into[c],
+                        Err(_) => word.len(),
+                    };
+                    for unit in report.units.iter_mut() {
+                        unit.input = remap(unit.input.start)..remap(unit.input.end);
                     }
-                    for e in exception.exceptions {
-                        if bmh::find(&lowercase, e.as_bytes()).is_some() {
-                            if latinize {
-                                return Ok(Some(&exception.latinized[i]));
+                    report.repaired = Some(repaired);
+                }
+                return Ok(true);
+            }
+        }
+        // Words are transliterated from the script they are written in
+        if !self.scripts.is_empty() {
+            let charset = detect_charset(word).charset;
+            if let Some((_, script)) = self.scripts.iter().find(|(c, _)| *c == charset) {
+                return script.convert_word(word, output, report);
+            }
+        }
+        if self.foreign_suffixes {
+            if let Some(converted) =
+                self.transliterate_suffix(word, output, report.as_deref_mut())?
+            {
+                return Ok(converted);
+            }
+        }
+        // Protected terms are kept regardless of charsets
+        if self.protected.matches(word) {
+            if let Some(report) = report {
+                report.action = WordAction::ProtectedSkipped;
+            }
+            return Ok(false);
+        }
+        let start = output.len();
+        let chars = word.chars().collect::<Vec<char>>();
+        let mut cursor_in: usize = 0;
+        let mut byte_in: usize = 0;
+        let mut force_process: bool = false;
+        if Self::foreign_pattern_exception(word) {
+            if self.force_links {
+                force_process = true;
+            } else {
+                if let Some(report) = report {
+                    report.action = WordAction::LinkSkipped;
+                }
+                return Ok(false);
+            }
+        }
+        let mut candidates: Vec<usize> = Vec::new();
+        let mut word_exceptions: Option<WordExceptions<'_>> = None;
+        'outer: while cursor_in < chars.len() {
+            // Candidates are ordered from bottom to catch digraphs first
+            self.matcher.find(&chars[cursor_in..], &mut candidates);
+            'inner: for &i in candidates.iter() {
+                let Character { value: c, case: lc } = &self.charset_from[i];
+                // Unmarked digraphs are authoritative when round-tripping
+                if !self.skip_digraph && self.exceptions && !self.round_trip {
+                    if let Some((exception, found)) =
+                        self.digraph_exception(&chars, &mut word_exceptions, c, cursor_in)
+                    {
+                        let unit = output.len() - start;
+                        output.extend(exception.value.iter());
+                        if let Some(report) = report.as_deref_mut() {
+                            report.exception(found);
+                            if exception.value.len() == c.len() {
+                                // Each letter is kept separately
+                                let (mut from, mut into) = (byte_in, unit);
+                                for (a, b) in c.iter().zip(exception.value.iter()) {
+                                    report
+                                        .unit(from..from + a.len_utf8(), into..into + b.len_utf8());
+                                    from += a.len_utf8();
+                                    into += b.len_utf8();
+                                }
                             } else {
-                                return Ok(Some(&exception.cyrillic[i]));
+                                report.unit(
+                                    byte_in..byte_in + utf8_len(c),
+                                    unit..output.len() - start,
+                                );
                             }
                         }
+                        // Only the source letters are consumed, since the
+                        // exception value may be longer or shorter
+                        byte_in += utf8_len(c);
+                        cursor_in += c.len();
+                        continue 'outer;
                     }
                 }
+                // Check if digraph is preceded or followed by same case
+                if lc == &LetterCase::Mixed {
+                    let prev_lower = if cursor_in > 0 {
+                        Some(chars[cursor_in - 1].is_lowercase())
+                    } else {
+                        None
+                    };
+                    let next_lower = if cursor_in < chars.len() - 1 {
+                        Some(chars[cursor_in + 1].is_lowercase())
+                    } else {
+                        None
+                    };
+                    if matches!((prev_lower, next_lower), (_, Some(false))) {
+                        continue 'inner;
+                    }
+                }
+                // Exception is not found, proceed to transliterate
+                let unit = output.len() - start;
+                let value = &self.charset_into[i].value;
+                if self.round_trip && output.len() > start {
+                    // Letters of separate units would be read as a digraph
+                    let last = output.chars().last().expect("output should not be empty");
+                    if self.joined.contains(&[last, value[0]]) {
+                        output.push(charmaps::DIGRAPH_BREAK);
+                    }
+                }
+                output.extend(value.iter());
+                if let Some(report) = report.as_deref_mut() {
+                    report.unit(byte_in..byte_in + utf8_len(c), unit..output.len() - start);
+                }
+                byte_in += utf8_len(c);
+                cursor_in += c.len();
+                continue 'outer;
+            }
+            if !force_process && !self.force_foreign && chars[cursor_in].is_alphabetic() {
+                // Foreign character is found, return original
+                output.truncate(start);
+                if let Some(report) = report {
+                    report.units.clear();
+                    report.action = WordAction::ForeignSkipped {
+                        letter: chars[cursor_in],
+                    };
+                }
+                return Ok(false);
+            } else {
+                // Add found non-alphabetic or foreign character
+                let unit = output.len() - start;
+                let length = chars[cursor_in].len_utf8();
+                // Marker between letters of an entry only keeps them apart
+                let marker = self.round_trip
+                    && chars[cursor_in] == charmaps::DIGRAPH_BREAK
+                    && cursor_in > 0
+                    && cursor_in + 1 < chars.len()
+                    && self
+                        .digraphs
+                        .contains(&[chars[cursor_in - 1], chars[cursor_in + 1]]);
+                if !marker {
+                    output.push(chars[cursor_in]);
+                }
+                if let Some(report) = report.as_deref_mut() {
+                    report.unit(byte_in..byte_in + length, unit..output.len() - start);
+                }
+                byte_in += length;
+                cursor_in += 1;
             }
         }
-        Ok(None)
+        Ok(true)
     }
 
-    fn foreign_pattern_exception(word: &str) -> bool {
-        lazy_static! {
-            // Borrowed from https://stackoverflow.com/a/26093611
-            static ref RE_DOMAIN: Regex = Regex::new(
-                r"[a-zA-Z0-9][a-zA-Z0-9-]{1,61}[a-zA-Z0-9](?:\.[a-zA-Z]{2,})+"
-            ).unwrap();
-            // Borrowed from https://www.emailregex.com/
-            static ref RE_EMAIL: Regex =
-                Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,6}\b").unwrap();
-            // Borrowed from http://urlregex.com/
-            static ref RE_URL: Regex = Regex::new(
-                r"((http[s]?://)|(./)|(/))(?:[a-zA-Z]|[0-9]|[$-_@.&+]|[!*\(\),]|(?:%[0-9a-fA-F][0-9a-fA-F]))+"
-            ).unwrap();
-            // Borrowed from https://github.com/turanjanin/cirilizator
-            static ref RE_MEASUREMENT: Regex = Regex::new(
-                r"(\d+([\.,]\d)*)((K|°[FC]|[kKMGTPEY](ib|b|iB|B|Hz)|[pnμmcdhk]m[²³]?|m[²³]|[mcdkh][lg])|([zafpnμmcdhKMGTPEY]?([BVWJFSHCΩATNhlmg]|m[²³]|s[²]|cd|Pa|Wb|Hz|deg|rad)))"
-            ).unwrap();
-        }
-        RE_DOMAIN.is_match(word)
-            || RE_EMAIL.is_match(word)
-            || RE_URL.is_match(word)
-            || RE_MEASUREMENT.is_match(word)
+    /// Splits text into alternating runs of whitespace and words,
+    /// marking words with `true`
+    fn split_words(input: &str) -> impl Iterator<Item = (&str, bool)> {
+        fn next_occurence(input: &str, left: usize, match_text: bool) -> usize {
+            let criterion = match match_text {
+                true => |c: char| c.is_whitespace(),
+                false => |c: char| !c.is_whitespace(),
+            };
+            if let Some(res) = input[left..].find(criterion) {
+                cmp::min(left + res, input.len())
+            } else {
+                input.len()
+            }
+        }
+        let mut cursor_left = 0;
+        let mut match_text: bool = false;
+        std::iter::from_fn(move || {
+            while cursor_left < input.len() {
+                let cursor_right = next_occurence(input, cursor_left, match_text);
+                let segment = (&input[cursor_left..cursor_right], match_text);
+                cursor_left = cursor_right;
+                // Toggle between processing whitespace and other characters
+                match_text = !match_text;
+                if !segment.0.is_empty() {
+                    return Some(segment);
+                }
+            }
+            None
+        })
     }
 
     /// Try transliterating a single word (without spaces).
@@ -221,70 +848,33 @@ impl Transliterator {
     /// assert_eq!(t.process_word("Љубичица").unwrap(), "ǈubičica".to_owned());
     /// ```
     pub fn process_word(&self, word: &str) -> Result<String, Error> {
-        let mut out: Vec<u8> = vec![0; word.len() * 4];
-        let chars = word.chars().into_iter().collect::<Vec<char>>();
-        let mut cursor_in: usize = 0;
-        let mut cursor_out: usize = 0;
-        let mut force_process: bool = false;
-        if Self::foreign_pattern_exception(word) {
-            if self.force_links {
-                force_process = true;
-            } else {
-                return Ok(word.to_string());
-            }
-        }
-        'outer: while cursor_in < chars.len() {
-            'inner: for (i, Character { value: c, case: lc }) in
-                self.charset_from.iter().enumerate().rev()
-            {
-                if chars[cursor_in..].starts_with(c) {
-                    if !self.skip_digraph && self.exceptions {
-                        // If transliterating to latin8, transliterate exception too
-                        let latinize = self.charset_into == charmaps::LATIN_CLEAN_UNICODE
-                            || self.charset_into == charmaps::LATIN_DIRTY_UNICODE;
-                        // Start from bottom to catch digraphs first
-                        if let Some(exception) = Self::digraph_exception(&chars, c, latinize)? {
-                            cursor_out +=
-                                Self::chars_to_utf8(exception.value, &mut out[cursor_out..])?;
-                            cursor_in += exception.value.len();
-                            continue 'outer;
-                        }
-                    }
-                    // Check if digraph is preceded or followed by same case
-                    if lc == &LetterCase::Mixed {
-                        let prev_lower = if cursor_in > 0 {
-                            Some(chars[cursor_in - 1].is_lowercase())
-                        } else {
-                            None
-                        };
-                        let next_lower = if cursor_in < chars.len() - 1 {
-                            Some(chars[cursor_in + 1].is_lowercase())
-                        } else {
-                            None
-                        };
-                        if matches!((prev_lower, next_lower), (_, Some(false))) {
-                            continue 'inner;
-                        }
-                    }
-                    // Exception is not found, proceed to transliterate
-                    cursor_out +=
-                        Self::chars_to_utf8(self.charset_into[i].value, &mut out[cursor_out..])?;
-                    cursor_in += c.len();
-                    continue 'outer;
-                }
-            }
-            if !force_process && !self.force_foreign && chars[cursor_in].is_alphabetic() {
-                // Foreign character is found, return original
-                return Ok(word.to_string());
-            } else {
-                // Add found non-alphabetic or foreign character
-                cursor_out += Self::chars_to_utf8(&[chars[cursor_in]], &mut out[cursor_out..])?;
-                cursor_in += 1;
-            }
+        self.process_word_cow(word).map(Cow::into_owned)
+    }
+
+    /// Try transliterating a single word (without spaces), borrowing
+    /// the input if it is left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - Word to transliterate
+    /// # Examples
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use translitrs::{Transliterator, Charset};
+    ///
+    /// let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+    /// assert!(matches!(t.process_word_cow("Ljubičica").unwrap(), Cow::Owned(_)));
+    /// // Word is already in Cyrillic, so it is not copied
+    /// assert!(matches!(t.process_word_cow("Љубичица").unwrap(), Cow::Borrowed(_)));
+    /// ```
+    pub fn process_word_cow<'a>(&self, word: &'a str) -> Result<Cow<'a, str>, Error> {
+        let mut output = String::new();
+        if self.transliterate_word(word, &mut output, None)? && output != word {
+            Ok(Cow::Owned(output))
+        } else {
+            Ok(Cow::Borrowed(word))
         }
-        out.resize(cursor_out, 0);
-        let out = String::from_utf8(out)?;
-        Ok(out)
     }
 
     /// Try transliterating arbitrary text.
@@ -308,33 +898,293 @@ impl Transliterator {
     pub fn process<S: AsRef<str>>(&self, input: S) -> Result<String, Error> {
         let input = input.as_ref();
         let mut output = String::with_capacity(input.len());
-        let mut cursor_left = 0;
-        fn next_occurence(input: &str, left: usize, match_text: bool) -> usize {
-            let criterion = match match_text {
-                true => |c: char| c.is_whitespace(),
-                false => |c: char| !c.is_whitespace(),
+        self.process_into(input, &mut output)?;
+        Ok(output)
+    }
+
+    /// Try transliterating arbitrary text, borrowing the input if it is
+    /// left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to transliterate
+    /// # Examples
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use translitrs::{Transliterator, Charset};
+    ///
+    /// let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+    /// assert!(matches!(t.process_cow("Ово је ћирилица.").unwrap(), Cow::Borrowed(_)));
+    /// assert_eq!(t.process_cow("Ovo je latinica.").unwrap(), "Ово је латиница.");
+    /// ```
+    pub fn process_cow<'a>(&self, input: &'a str) -> Result<Cow<'a, str>, Error> {
+        let mut output: Option<String> = None;
+        let mut cursor = 0;
+        for (segment, is_word) in Self::split_words(input) {
+            if let Some(output) = output.as_mut() {
+                if !is_word || !self.transliterate_word(segment, output, None)? {
+                    output.push_str(segment);
+                }
+            } else if is_word {
+                // Copy the input only after first word has been changed
+                if let Cow::Owned(word) = self.process_word_cow(segment)? {
+                    let mut owned = String::with_capacity(input.len());
+                    owned.push_str(&input[..cursor]);
+                    owned.push_str(&word);
+                    output = Some(owned);
+                }
+            }
+            cursor += segment.len();
+        }
+        Ok(match output {
+            Some(output) => Cow::Owned(output),
+            None => Cow::Borrowed(input),
+        })
+    }
+
+    /// Try transliterating arbitrary text, appending the result to a
+    /// caller-owned buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to transliterate
+    /// * `output` - Buffer to append transliterated text to
+    /// # Examples
+    ///
+    /// ```
+    /// use translitrs::{Transliterator, Charset};
+    ///
+    /// let t = Transliterator::new(Charset::Cyrillic, Charset::Latin, false, false, false);
+    /// let mut buffer = String::new();
+    /// t.process_into("Џем од ", &mut buffer).unwrap();
+    /// t.process_into("шљива", &mut buffer).unwrap();
+    /// assert_eq!(buffer, "Džem od šljiva");
+    /// ```
+    pub fn process_into(&self, input: &str, output: &mut String) -> Result<(), Error> {
+        output.reserve(input.len());
+        for (segment, is_word) in Self::split_words(input) {
+            // Skip processing space characters
+            if !is_word || !self.transliterate_word(segment, output, None)? {
+                output.push_str(segment);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns report of how each word of a text is transliterated,
+    /// including words which are kept as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to transliterate
+    /// # Examples
+    ///
+    /// ```
+    /// use translitrs::{Transliterator, WordAction};
+    ///
+    /// let t = Transliterator::default();
+    /// let report = t.explain("Injekcija, példa").unwrap();
+    /// assert_eq!(report[0].output, "Инјекција,");
+    /// assert_eq!(
+    ///     report[0].action,
+    ///     WordAction::ExceptionApplied { exceptions: vec!["injekc".to_owned()] }
+    /// );
+    /// assert_eq!((report[1].start, report[1].end), (11, 17));
+    /// assert_eq!(report[1].action, WordAction::ForeignSkipped { letter: 'é' });
+    /// ```
+    pub fn explain(&self, input: &str) -> Result<Vec<WordReport>, Error> {
+        let mut reports = Vec::new();
+        let mut cursor = 0;
+        for (segment, is_word) in Self::split_words(input) {
+            if is_word {
+                reports.push(self.explain_word(cursor, segment)?);
+            }
+            cursor += segment.len();
+        }
+        Ok(reports)
+    }
+
+    fn explain_word(&self, start: usize, word: &str) -> Result<WordReport, Error> {
+        let mut report = WordReport::new(start, word);
+        let mut output = String::new();
+        if !self.transliterate_word(word, &mut output, Some(&mut report))? {
+            output = word.to_owned();
+            report.units = Span::identity(word, 0, 0);
+        }
+        report.output = output;
+        Ok(report)
+    }
+
+    /// Finds words where a Latin digraph follows a known prefix or root,
+    /// so it likely has to be kept as two separate letters, but none of
+    /// the digraph exceptions or prefix rules decide it. Such words are
+    /// reported together with their line and surrounding words, so they
+    /// can be reviewed.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Latin text to check
+    /// # Example
+    ///
+    /// ```
+    /// use translitrs::Transliterator;
+    ///
+    /// let t = Transliterator::default();
+    /// // "injekc" is a known exception, "injunkc" is not
+    /// let found = t.ambiguous_digraphs("Injekcija leka.\nSud je izdao injunkciju juče.");
+    /// assert_eq!(found.len(), 1);
+    /// assert_eq!((found[0].word.as_str(), found[0].digraph.as_str()), ("injunkciju", "nj"));
+    /// assert_eq!((found[0].line, found[0].prefix.as_str()), (2, "in"));
+    /// assert_eq!(found[0].context, "Sud je izdao injunkciju juče.");
+    /// ```
+    pub fn ambiguous_digraphs(&self, input: &str) -> Vec<AmbiguousDigraph> {
+        const CONTEXT: usize = 3;
+        if !self.exceptions {
+            return Vec::new();
+        }
+        let mut words = Vec::new();
+        let mut cursor = 0;
+        for (segment, is_word) in Self::split_words(input) {
+            if is_word {
+                words.push((cursor, segment));
+            }
+            cursor += segment.len();
+        }
+        let groups = self.exception_matcher.groups();
+        let mut found = Vec::new();
+        for (i, &(start, word)) in words.iter().enumerate() {
+            let (prefix, group) = match ambiguous::prefix_digraph(groups, word) {
+                Some(prefix) => prefix,
+                None => continue,
             };
-            if let Some(res) = input[left..].find(criterion) {
-                cmp::min(left + res, input.len())
-            } else {
-                input.len()
+            let chars = word.chars().collect::<Vec<char>>();
+            if self.exception_matcher.find(&chars).group(group).is_some()
+                || self.exception_matcher.denied(group, word)
+                || self.protected.matches(word)
+                || Self::foreign_pattern_exception(word)
+            {
+                continue;
             }
+            let first = i.saturating_sub(CONTEXT);
+            let last = cmp::min(i + CONTEXT, words.len() - 1);
+            let context_end = words[last].0 + words[last].1.len();
+            found.push(AmbiguousDigraph {
+                word: word.to_owned(),
+                digraph: groups[group].name.clone(),
+                prefix: prefix.to_owned(),
+                split: word.len() - word.trim_start_matches(|c: char| !c.is_alphabetic()).len()
+                    + prefix.len(),
+                start,
+                end: start + word.len(),
+                line: input[..start].matches('\n').count() + 1,
+                context: input[words[first].0..context_end]
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            });
         }
-        let mut match_text: bool = false;
-        while cursor_left < input.len() {
-            let cursor_right = next_occurence(input, cursor_left, match_text);
-            if match_text {
-                let res = self.process_word(&input[cursor_left..cursor_right])?;
-                output.push_str(&res);
+        found
+    }
+
+    /// Compares transliteration of a text with its expected
+    /// transliteration line by line and word by word, recording likely
+    /// cause of each mismatch. Lines with a different number of words
+    /// are recorded as a single misaligned mismatch.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to transliterate
+    /// * `expected` - Correct transliteration of the text
+    /// # Example
+    ///
+    /// ```
+    /// use translitrs::{MismatchCause, Transliterator};
+    ///
+    /// let t = Transliterator::default();
+    /// let evaluation = t.evaluate("Njiva nadživela", "Њива наџивела").unwrap();
+    /// assert_eq!(evaluation.accuracy(), 0.5);
+    /// assert_eq!(evaluation.mismatches[0].output, "надживела");
+    /// assert_eq!(evaluation.mismatches[0].cause, MismatchCause::DigraphException);
+    /// ```
+    pub fn evaluate(&self, input: &str, expected: &str) -> Result<Evaluation, Error> {
+        let mut reports = self.explain(input)?.into_iter().peekable();
+        let mut input_lines = input.split('\n');
+        let mut expected_lines = expected.split('\n');
+        let mut evaluation = Evaluation::default();
+        let (mut line, mut end) = (0, 0);
+        loop {
+            let (input_line, expected_line) = match (input_lines.next(), expected_lines.next()) {
+                (None, None) => break,
+                (input_line, expected_line) => {
+                    (input_line.unwrap_or(""), expected_line.unwrap_or(""))
+                }
+            };
+            let start = end;
+            line += 1;
+            end += input_line.len() + 1;
+            let mut line_reports = Vec::new();
+            while let Some(report) = reports.next_if(|report| report.start < end) {
+                line_reports.push(report);
+            }
+            let expected_words = Self::split_words(expected_line)
+                .filter_map(|(segment, is_word)| if is_word { Some(segment) } else { None })
+                .collect::<Vec<_>>();
+            if line_reports.len() != expected_words.len() {
+                evaluation.misaligned(line, start, input_line, line_reports, expected_line);
+                continue;
+            }
+            for (report, expected) in line_reports.into_iter().zip(expected_words) {
+                evaluation.compare(input, report, expected);
+            }
+        }
+        Ok(evaluation)
+    }
+
+    /// Try transliterating arbitrary text, returning byte ranges of each
+    /// unit and word of the input together with their transliteration.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to transliterate
+    /// # Examples
+    ///
+    /// ```
+    /// use translitrs::Transliterator;
+    ///
+    /// let t = Transliterator::default();
+    /// let aligned = t.process_aligned("Njiva i polje").unwrap();
+    /// assert_eq!(aligned.output, "Њива и поље");
+    /// // Highlight of "polje" is carried over to "поље"
+    /// assert_eq!(&aligned.output[aligned.output_range(8..13)], "поље");
+    /// assert_eq!((aligned.words[0].input.clone(), aligned.words[0].output.clone()), (0..5, 0..8));
+    /// ```
+    pub fn process_aligned(&self, input: &str) -> Result<Alignment, Error> {
+        let mut aligned = Alignment {
+            output: String::with_capacity(input.len()),
+            ..Alignment::default()
+        };
+        let mut cursor = 0;
+        for (segment, is_word) in Self::split_words(input) {
+            let start = aligned.output.len();
+            if is_word {
+                let report = self.explain_word(cursor, segment)?;
+                aligned.output.push_str(&report.output);
+                aligned.units.extend(report.units.into_iter().map(|s| Span {
+                    input: cursor + s.input.start..cursor + s.input.end,
+                    output: start + s.output.start..start + s.output.end,
+                }));
+                aligned.words.push(Span {
+                    input: cursor..cursor + segment.len(),
+                    output: start..aligned.output.len(),
+                });
             } else {
-                // Skip processing space characters
-                output.push_str(&input[cursor_left..cursor_right]);
+                aligned.output.push_str(segment);
+                aligned.units.extend(Span::identity(segment, cursor, start));
             }
-            cursor_left = cursor_right;
-            // Toggle between processing whitespace and other characters
-            match_text = !match_text;
+            cursor += segment.len();
         }
-        Ok(output)
+        Ok(aligned)
     }
 }
 
@@ -342,7 +1192,7 @@ impl Transliterator {
 mod tests {
     use super::*;
 
-    const EXAMPLES: &'static [(&str, &str, bool)] = &[
+    pub(super) const EXAMPLES: &[(&str, &str, bool)] = &[
         ("", "", true),
         ("1234567890", "1234567890", true),
         (
@@ -404,78 +1254,337 @@ mod tests {
     }
 
     #[test]
-    fn test_chars_to_utf8() -> Result<(), Error> {
-        let mut output: Vec<u8> = vec![0; 100];
-        let len = Transliterator::chars_to_utf8(
-            &['В', 'у', 'к', ' ', 'к', 'a', 'r', 'a', 'd', 'ž'],
-            &mut output,
-        )?;
-        assert_eq!(String::from_utf8_lossy(&output[..len]), "Вук кaradž");
+    fn test_builder() -> Result<(), Error> {
+        let t = Transliterator::builder()
+            .charset_from(Charset::Latin)
+            .charset_into(Charset::Cyrillic)
+            .force_foreign(true)
+            .build()?;
+        assert_eq!(t.process("példa")?, "пéлда");
+        let t = Transliterator::builder().force_links(true).build()?;
+        assert_eq!(t.process("www.primer.rs")?, "www.пример.рс");
+        let t = Transliterator::builder().skip_digraph(true).build()?;
+        assert_eq!(t.process("injekcija")?, "ињекција");
+        assert_eq!(Transliterator::builder().build()?.process("injekcija")?, "инјекција");
+        let t = Transliterator::builder()
+            .protected_terms(ProtectedTerms::builtin())
+            .protected_ignore_case(true)
+            .protected_suffixes(true)
+            .build()?;
+        assert_eq!(t.process("iPhone-u i USB (nato)")?, "iPhone-u и USB (nato)");
+        assert_eq!(
+            t.process("Google, Adobe, Nokia, USB i NATO u Google-u")?,
+            "Google, Adobe, Nokia, USB и NATO у Google-u"
+        );
+        let t = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin)
+            .protected_terms(ProtectedTerms::parse("Дунав")?)
+            .build()?;
+        assert_eq!(t.process("Дунав у Дунаву")?, "Дунав u Dunavu");
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Error> {
+        let cyrillic = "Коњугација и конјугација, НАДЖИВЕТИ Ђорђа, Нјујорк, поджупан шилјак";
+        let into_latin = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin)
+            .round_trip(true)
+            .build()?;
+        let latin = into_latin.process(cyrillic)?;
+        assert_eq!(
+            latin,
+            "Konjugacija i kon\u{200C}jugacija, NAD\u{200C}ŽIVETI Đorđa, N\u{200C}jujork, \
+             pod\u{200C}župan šil\u{200C}jak"
+        );
+        let into_cyrillic = Transliterator::builder().round_trip(true).build()?;
+        assert_eq!(into_cyrillic.process(&latin)?, cyrillic);
+        let aligned = into_cyrillic.process_aligned("kon\u{200C}jug")?;
+        assert_eq!(aligned.output_range(3..6), 6..6);
+        // Markers are kept outside of round-trip mode
+        let t = Transliterator::default();
+        assert_eq!(t.process("kon\u{200C}jug")?, "кон\u{200C}југ");
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignorable() -> Result<(), Error> {
+        let t = Transliterator::default();
+        assert_eq!(
+            t.process("Ban\u{AD}ja, kon\u{AD}jugacija, nad\u{200D}živeti pe\u{301}sme")?,
+            "Бањ\u{AD}а, кон\u{AD}југација, над\u{200D}живети пе\u{301}сме"
+        );
+        let aligned = t.process_aligned("n\u{AD}jiva")?;
+        assert_eq!(aligned.output, "њ\u{AD}ива");
+        assert_eq!(
+            aligned.units[0],
+            Span {
+                input: 0..4,
+                output: 0..4
+            }
+        );
+        assert_eq!(aligned.output_range(4..5), 4..6);
+        let t = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin)
+            .build()?;
+        assert_eq!(t.process("\u{2060}Ђа\u{301}ци")?, "\u{2060}Đa\u{301}ci");
+        // Ignored characters are matched as any other
+        let t = Transliterator::builder().ignorable(Vec::new()).build()?;
+        assert_eq!(t.process("Ban\u{AD}ja")?, "Бан\u{AD}ја");
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalization() -> Result<(), Error> {
+        let t = Transliterator::default();
+        assert_eq!(t.process("C\u{30C}aj i s\u{30C}ljive")?, "Чај и шљиве");
+        let aligned = t.process_aligned("c\u{30C}aj")?;
+        assert_eq!(
+            aligned.units[0],
+            Span {
+                input: 0..3,
+                output: 0..2
+            }
+        );
+        let builder = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin);
+        let t = builder.clone().normalization(Normalization::Nfd).build()?;
+        assert_eq!(t.process("Чај и ђак")?, "C\u{30C}aj i đak");
+        assert_eq!(t.process_aligned("Чај")?.output_range(0..2), 0..3);
+        // Skipped links are kept unless they are transliterated
+        assert_eq!(t.process("https://čaj.rs")?, "https://čaj.rs");
+        let t = builder
+            .clone()
+            .normalization(Normalization::Nfd)
+            .force_links(true)
+            .build()?;
+        assert_eq!(t.process("https://чај.рс")?, "https://c\u{30C}aj.rs");
+        let t = builder.normalization(Normalization::Nfc).build()?;
+        assert_eq!(t.process("пе\u{301}сме piše")?, "pésme piše");
+        // Skipped words which are already normalized are borrowed
+        assert!(matches!(t.process_cow("ovo je tekst")?, Cow::Borrowed(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_accents() -> Result<(), Error> {
+        let t = Transliterator::builder().accents(true).build()?;
+        assert_eq!(
+            t.process("rȅka, mlȃd, vòda, sȑce, ćȁr")?,
+            "ре\u{30F}ка, мла\u{311}д, во\u{300}да, ср\u{30F}це, ћа\u{30F}р"
+        );
+        assert_eq!(t.process_aligned("rȅka")?.output_range(1..3), 2..6);
+        let builder = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin)
+            .accents(true);
+        assert_eq!(builder.build()?.process("рѐка")?, "re\u{300}ka");
+        let t = builder.normalization(Normalization::Nfc).build()?;
+        assert_eq!(t.process("рѐка, ср\u{30F}це")?, "rèka, sȑce");
+        // Accented letters are foreign unless enabled
+        assert_eq!(Transliterator::default().process("rȅka")?, "rȅka");
+        Ok(())
+    }
+
+    #[test]
+    fn test_foreign_suffixes() -> Result<(), Error> {
+        let t = Transliterator::builder()
+            .protected_terms(ProtectedTerms::builtin())
+            .protected_suffixes(true)
+            .foreign_suffixes(true)
+            .build()?;
+        assert_eq!(
+            t.process("Twitter-om, iPhone-a i USB-u, Twitter'om Twitter-xyz")?,
+            "Twitter-ом, iPhone-а и USB-у, Twitter'ом Twitter-xyz"
+        );
+        // Roots written only with Serbian Latin letters are kept too
+        let t = Transliterator::builder().foreign_suffixes(true).build()?;
+        assert_eq!(t.process("Facebook-u i Google-om")?, "Facebook-у и Google-ом");
+        let report = t.explain("Twitter-om,")?;
+        assert_eq!(
+            report[0].action,
+            WordAction::SuffixConverted {
+                root: "Twitter".to_owned()
+            }
+        );
+        assert_eq!(report[0].output, "Twitter-ом,");
+        let t = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin)
+            .foreign_suffixes(true)
+            .build()?;
+        assert_eq!(t.process("Twitter-у и Твитер-у")?, "Twitter-u i Tviter-u");
+        // Whole word is kept unless enabled
+        assert_eq!(Transliterator::default().process("Twitter-om")?, "Twitter-om");
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair_homoglyphs() -> Result<(), Error> {
+        let t = Transliterator::builder().repair_homoglyphs(true).build()?;
+        // Cyrillic "а" in links and email addresses
+        let text = "https://pаypal.rs pоdrska@pаypal.rs 5kм";
+        assert_eq!(t.process(text)?, text);
+        assert_eq!(t.repaired_words(), 0);
+        let t = Transliterator::builder()
+            .repair_homoglyphs(true)
+            .force_links(true)
+            .build()?;
+        assert_eq!(t.process("https://pаypal.rs")?, "хттпс://паyпал.рс");
+        assert_eq!(t.repaired_words(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<(), Error> {
+        let t = Transliterator::builder()
+            .protected_terms(ProtectedTerms::builtin())
+            .repair_homoglyphs(true)
+            .build()?;
+        let report = t.explain(" iPhone  primer.rs Јaвор nadživeti\n123")?;
+        let actions = report
+            .iter()
+            .map(|r| (r.start, r.end, r.action.clone(), r.output.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                (1, 7, WordAction::ProtectedSkipped, "iPhone"),
+                (9, 18, WordAction::LinkSkipped, "primer.rs"),
+                (19, 28, WordAction::ForeignSkipped { letter: 'Ј' }, "Јавор"),
+                (
+                    29,
+                    39,
+                    WordAction::ExceptionApplied {
+                        exceptions: vec!["nadživ".to_owned()]
+                    },
+                    "надживети"
+                ),
+                (40, 43, WordAction::Converted, "123"),
+            ]
+        );
+        assert_eq!(report[2].repaired.as_deref(), Some("Јавор"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixed_scripts() -> Result<(), Error> {
+        let t = Transliterator::builder().mixed_scripts(true).build()?;
+        assert_eq!(
+            t.process("Ljubičica и Ђурђевак, ǌiva i injekcija (example)")?,
+            "Љубичица и Ђурђевак, њива и инјекција (example)"
+        );
+        let t = Transliterator::builder()
+            .charset_into(Charset::LatinUnicode)
+            .mixed_scripts(true)
+            .build()?;
+        assert_eq!(t.process("Ljubičica и Њива, ǌiva")?, "ǈubičica i ǋiva, ǌiva");
         Ok(())
     }
 
     #[test]
     fn test_digraph_exception() -> Result<(), Error> {
+        let t_cyr = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        let t_lat8 =
+            Transliterator::new(Charset::Latin, Charset::LatinUnicode, false, false, false);
         assert_eq!(
-            Transliterator::digraph_exception(
-                &['a', 'D', 'r', 'u', 'g', 'd', 'j', 'e', 'd'],
-                &['đ'],
-                false
-            )?
-            .unwrap()
-            .value,
+            t_cyr
+                .digraph_exception(
+                    &['a', 'D', 'r', 'u', 'g', 'd', 'j', 'e', 'd'],
+                    &mut None,
+                    &['đ'],
+                    5
+                )
+                .unwrap()
+                .0
+                .value
+                .as_ref(),
             &['д', 'ј']
         );
         assert_eq!(
-            Transliterator::digraph_exception(
-                &['a', 'D', 'r', 'u', 'g', 'd', 'j', 'e', 'd'],
-                &['đ'],
-                true
-            )?
-            .unwrap()
-            .value,
+            t_lat8
+                .digraph_exception(
+                    &['a', 'D', 'r', 'u', 'g', 'd', 'j', 'e', 'd'],
+                    &mut None,
+                    &['đ'],
+                    5
+                )
+                .unwrap()
+                .0
+                .value
+                .as_ref(),
             &['d', 'j']
         );
         assert_eq!(
-            Transliterator::digraph_exception(
-                &['n', 'a', 'D', 'ž', 'i', 'v', 'e', 't', 'i'],
-                &['D', 'ž'],
-                false
-            )?
-            .unwrap()
-            .value,
+            t_cyr
+                .digraph_exception(
+                    &['n', 'a', 'D', 'ž', 'i', 'v', 'e', 't', 'i'],
+                    &mut None,
+                    &['D', 'ž'],
+                    2
+                )
+                .unwrap()
+                .0
+                .value
+                .as_ref(),
             &['Д', 'ж']
         );
         assert_eq!(
-            Transliterator::digraph_exception(
-                &['n', 'a', 'D', 'ž', 'i', 'v', 'e', 't', 'i'],
-                &['D', 'ž'],
-                true
-            )?
-            .unwrap()
-            .value,
+            t_lat8
+                .digraph_exception(
+                    &['n', 'a', 'D', 'ž', 'i', 'v', 'e', 't', 'i'],
+                    &mut None,
+                    &['D', 'ž'],
+                    2
+                )
+                .unwrap()
+                .0
+                .value
+                .as_ref(),
             &['D', 'ž']
         );
         assert_eq!(
-            Transliterator::digraph_exception(
-                &['d', 'a', 'N', 'J', 'o', 'n', 'i'],
-                &['N', 'J'],
-                false
-            )?
-            .unwrap()
-            .value,
+            t_cyr
+                .digraph_exception(&['d', 'a', 'N', 'J', 'o', 'n', 'i'], &mut None, &['N', 'J'], 2)
+                .unwrap()
+                .0
+                .value
+                .as_ref(),
             &['Н', 'Ј']
         );
         assert_eq!(
-            Transliterator::digraph_exception(
-                &['d', 'a', 'N', 'J', 'o', 'n', 'i'],
-                &['N', 'J'],
-                true
-            )?
-            .unwrap()
-            .value,
+            t_lat8
+                .digraph_exception(&['d', 'a', 'N', 'J', 'o', 'n', 'i'], &mut None, &['N', 'J'], 2)
+                .unwrap()
+                .0
+                .value
+                .as_ref(),
             &['N', 'J']
         );
+        // Split marker limits exception to a single digraph
+        let t = Transliterator::builder()
+            .exceptions(ExceptionList::parse("[nj]\nkon|jug\n!konjug\n")?)
+            .build()?;
+        assert_eq!(t.process("konjkonjugacija")?, "коњконјугација");
+        // Prefix rules cover words missing from exception lists, but
+        // only when enabled, as they don't match the output of earlier
+        // versions
+        let t_rules = Transliterator::builder().prefix_rules(true).build()?;
+        assert_eq!(
+            t_rules.process("Podžbunje i odžak, predjezičan, podžadžak")?,
+            "Поджбуње и оџак, предјезичан, поджаџак"
+        );
+        assert_eq!(
+            t_cyr.process("Podžbunje i odžak, predjezičan, podžadžak")?,
+            "Поџбуње и оџак, пређезичан, поџаџак"
+        );
+        for t in [&t_cyr, &t_rules] {
+            assert_eq!(t.process("Nadja, Odžaci, Predjašnji")?, "Нађа, Оџаци, Пређашњи");
+        }
         Ok(())
     }
 
@@ -505,11 +1614,11 @@ mod tests {
     #[test]
     fn test_skip_foreign() -> Result<(), Error> {
         let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
-        for text in vec!["example", "例子", "مثال", "példa"] {
+        for text in ["example", "例子", "مثال", "példa"] {
             assert_eq!(text, t.process_word(text)?);
         }
         let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, true, false);
-        for (text, expected) in vec![
+        for (text, expected) in [
             ("example", "еxампле"),
             ("例子", "例子"),
             ("مثال", "مثال"),
@@ -520,6 +1629,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_process_cow() -> Result<(), Error> {
+        let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        for text in ["", " ", "Већ ћирилица", "123 ?!", "example.com"] {
+            assert!(matches!(t.process_cow(text)?, Cow::Borrowed(_)));
+            assert!(matches!(t.process_word_cow(text)?, Cow::Borrowed(_)));
+        }
+        for (lat, cyr, _) in EXAMPLES {
+            assert_eq!(t.process_cow(lat)?, *cyr);
+        }
+        assert_eq!(t.process_cow("  Već  latinica ")?, "  Већ  латиница ");
+        let mut buffer = "Почетак: ".to_owned();
+        t.process_into("Ljubičica, example.com", &mut buffer)?;
+        assert_eq!(buffer, "Почетак: Љубичица, example.com");
+        Ok(())
+    }
+
     #[test]
     fn test_digraph_capitalization() -> Result<(), Error> {
         let t_lat_cyr = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
@@ -534,7 +1660,7 @@ mod tests {
         let t_cyr_lat = Transliterator::new(Charset::Cyrillic, Charset::Latin, false, false, false);
         let t_cyr_lat8 =
             Transliterator::new(Charset::Cyrillic, Charset::LatinUnicode, false, false, false);
-        for (latin, latin8, cyrillic) in vec![
+        for (latin, latin8, cyrillic) in [
             // Љ
             ("Ljubiša", "ǈubiša", "Љубиша"),
             ("ljubiša", "ǉubiša", "љубиша"),
@@ -0,0 +1,124 @@
+use std::ops::Range;
+
+/// Byte range of a part of the input and of its transliteration
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub input: Range<usize>,
+    pub output: Range<usize>,
+}
+
+impl Span {
+    /// Returns spans of a text mapped onto itself, one for each character
+    pub(super) fn identity(text: &str, input: usize, output: usize) -> Vec<Self> {
+        text.char_indices()
+            .map(|(i, c)| Self {
+                input: input + i..input + i + c.len_utf8(),
+                output: output + i..output + i + c.len_utf8(),
+            })
+            .collect()
+    }
+}
+
+/// Transliterated text together with positions of its parts in the
+/// input text
+///
+/// Units are charmap entries (eg. "Lj" transliterated into "Љ") and
+/// single characters which are kept as is, so they cover the whole input
+/// and output in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Alignment {
+    pub output: String,
+    /// Spans of each transliterated unit
+    pub units: Vec<Span>,
+    /// Spans of each word, excluding whitespace
+    pub words: Vec<Span>,
+}
+
+impl Alignment {
+    /// Returns range of output covering all units which overlap the
+    /// input range, or the position of a unit boundary for empty ranges
+    pub fn output_range(&self, input: Range<usize>) -> Range<usize> {
+        Self::map(&self.units, input, |s| &s.input, |s| &s.output, self.output.len())
+    }
+
+    /// Returns range of input covering all units which overlap the
+    /// output range, or the position of a unit boundary for empty ranges
+    pub fn input_range(&self, output: Range<usize>) -> Range<usize> {
+        let end = self.units.last().map(|s| s.input.end).unwrap_or(0);
+        Self::map(&self.units, output, |s| &s.output, |s| &s.input, end)
+    }
+
+    fn map(
+        units: &[Span],
+        range: Range<usize>,
+        from: fn(&Span) -> &Range<usize>,
+        into: fn(&Span) -> &Range<usize>,
+        len: usize,
+    ) -> Range<usize> {
+        // First unit starting at or after the range end
+        let last = units.partition_point(|s| from(s).start < range.end);
+        if range.is_empty() {
+            let start = units.get(last).map(|s| into(s).start).unwrap_or(len);
+            return start..start;
+        }
+        // First unit ending after the range start
+        let first = units.partition_point(|s| from(s).end <= range.start);
+        match first < last {
+            true => into(&units[first]).start..into(&units[last - 1]).end,
+            false => len..len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate::{Charset, Error, Transliterator};
+
+    fn spans(spans: &[Span]) -> Vec<(Range<usize>, Range<usize>)> {
+        spans
+            .iter()
+            .map(|s| (s.input.clone(), s.output.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_align() -> Result<(), Error> {
+        let t = Transliterator::default();
+        let aligned = t.process_aligned("Ljubav, nadživeti example.com")?;
+        assert_eq!(aligned.output, "Љубав, надживети example.com");
+        assert_eq!(spans(&aligned.words), vec![(0..7, 0..11), (8..18, 12..30), (19..30, 31..42)]);
+        assert_eq!(
+            spans(&aligned.units[..4]),
+            vec![(0..2, 0..2), (2..3, 2..4), (3..4, 4..6), (4..5, 6..8)]
+        );
+        // Exception keeps "dž" as separate letters
+        assert_eq!(aligned.output_range(11..13), 18..20);
+        assert_eq!(aligned.output_range(12..14), 18..22);
+        assert_eq!(aligned.output_range(0..1), 0..2);
+        assert_eq!(aligned.output_range(1..1), 2..2);
+        assert_eq!(aligned.output_range(30..30), 42..42);
+        assert_eq!(aligned.input_range(0..1), 0..2);
+        assert_eq!(aligned.input_range(31..42), 19..30);
+        // Units of each character within repaired and skipped words
+        let t = Transliterator::builder()
+            .charset_from(Charset::Cyrillic)
+            .charset_into(Charset::Latin)
+            .repair_homoglyphs(true)
+            .build()?;
+        let aligned = t.process_aligned("Њaњa é")?;
+        assert_eq!(aligned.output, "Njanja é");
+        assert_eq!(
+            spans(&aligned.units),
+            vec![
+                (0..2, 0..2),
+                (2..3, 2..3),
+                (3..5, 3..5),
+                (5..6, 5..6),
+                (6..7, 6..7),
+                (7..9, 7..9)
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::charmaps;
+use super::exceptions::ExceptionGroup;
+
+/// Latin digraph which may have to be written as two separate letters,
+/// found in a word which no digraph exception applies to
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AmbiguousDigraph {
+    pub word: String,
+    /// Name of the digraph exception group, ie. the digraph itself
+    pub digraph: String,
+    /// Prefix or Latin root preceding the second letter of the digraph
+    pub prefix: String,
+    /// Byte index of the split between letters of the digraph within
+    /// the word
+    pub split: usize,
+    /// Byte index of the word within the text
+    pub start: usize,
+    /// Byte index after the word within the text
+    pub end: usize,
+    /// Line of the text containing the word, starting from 1
+    pub line: usize,
+    /// Words surrounding the word within the text
+    pub context: String,
+}
+
+impl AmbiguousDigraph {
+    /// Returns exception string which keeps letters of the digraph
+    /// separate in this word form, with punctuation removed
+    pub fn exception(&self) -> String {
+        let (before, after) = self.word.split_at(self.split);
+        let before = before.trim_start_matches(|c: char| !c.is_alphabetic());
+        let after = after.trim_end_matches(|c: char| !c.is_alphabetic());
+        format!("{}|{}", before, after).to_lowercase()
+    }
+}
+
+impl fmt::Display for AmbiguousDigraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}-{}\t{}\t{}\t{}",
+            self.line, self.start, self.end, self.word, self.digraph, self.context
+        )
+    }
+}
+
+/// Returns prefix and index of the exception group of the digraph
+/// formed by its last letter, if a word starts with one of the prefixes
+/// of group rules or with a known Latin root
+pub(super) fn prefix_digraph<'a>(
+    groups: &'a [ExceptionGroup],
+    word: &str,
+) -> Option<(&'a str, usize)> {
+    let word = word
+        .trim_start_matches(|c: char| !c.is_alphabetic())
+        .to_lowercase();
+    groups.iter().enumerate().find_map(|(g, group)| {
+        let roots = charmaps::DIGRAPH_ROOTS
+            .iter()
+            .filter(|(_, digraph)| *digraph == group.name)
+            .map(|(root, _)| *root);
+        group
+            .rule
+            .prefixes
+            .iter()
+            .map(String::as_str)
+            .chain(roots)
+            .find(|prefix| {
+                let last = prefix.len() - prefix.chars().last().map_or(0, char::len_utf8);
+                word.starts_with(prefix) && word[last..].starts_with(group.name.as_str())
+            })
+            .map(|prefix| (prefix, g))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate::{Charset, Error, ExceptionList, Transliterator};
+
+    #[test]
+    fn test_prefix_digraph() {
+        let groups = ExceptionGroup::builtin(false);
+        let found = |word| prefix_digraph(&groups, word).map(|(p, g)| (p, groups[g].name.as_str()));
+        assert_eq!(found("PODŽANR"), Some(("pod", "dž")));
+        assert_eq!(found("(odjednom"), Some(("od", "dj")));
+        assert_eq!(found("injunkcija"), Some(("in", "nj")));
+        assert_eq!(found("predjelo"), Some(("pred", "dj")));
+        assert_eq!(found("podela"), None);
+        assert_eq!(found("Anja"), None);
+        assert_eq!(found("od"), None);
+    }
+
+    #[test]
+    fn test_ambiguous_digraphs() -> Result<(), Error> {
+        let text = "Nadživeo je podžbun, injunkcija i (Podjunačen).\nOdžak, injunkcije";
+        let reported = |t: &Transliterator| {
+            t.ambiguous_digraphs(text)
+                .into_iter()
+                .map(|a| (a.word, a.start))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            reported(&Transliterator::default()),
+            vec![
+                ("podžbun,".to_owned(), 13),
+                ("injunkcija".to_owned(), 23),
+                ("(Podjunačen).".to_owned(), 36),
+                ("injunkcije".to_owned(), 59),
+            ]
+        );
+        // Words decided by prefix rules are not reported once enabled
+        let t = Transliterator::builder().prefix_rules(true).build()?;
+        let found = t
+            .ambiguous_digraphs(text)
+            .into_iter()
+            .map(|a| (a.word, a.digraph, a.start, a.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("injunkcija".to_owned(), "nj".to_owned(), 23, 1),
+                ("injunkcije".to_owned(), "nj".to_owned(), 59, 2),
+            ]
+        );
+        // Words covered by user-supplied exceptions are not reported
+        let t = Transliterator::builder()
+            .prefix_rules(true)
+            .exceptions(ExceptionList::parse("[nj]\ninjunkc\n")?)
+            .build()?;
+        assert!(t.ambiguous_digraphs(text).is_empty());
+        let found = Transliterator::default().ambiguous_digraphs("(Injunkcija),");
+        assert_eq!(found[0].split, 3);
+        assert_eq!(found[0].exception(), "in|junkcija");
+        let t = Transliterator::new(Charset::Cyrillic, Charset::Latin, false, false, false);
+        assert!(t.ambiguous_digraphs(text).is_empty());
+        Ok(())
+    }
+}
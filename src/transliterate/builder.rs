@@ -0,0 +1,340 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+use super::exceptions::{ExceptionGroup, ExceptionList, ExceptionMatcher};
+use super::protected::{ProtectedTerms, TermMatcher};
+use super::trie::CharmapTrie;
+use super::{charmaps, digraphs, Charset, Error, Normalization, Transliterator};
+
+/// Builder of a [`Transliterator`] with named options
+///
+/// By default, text is transliterated from Latin to Cyrillic with all
+/// features enabled.
+///
+/// # Example
+///
+/// ```
+/// use translitrs::{Charset, Transliterator};
+///
+/// let t = Transliterator::builder()
+///     .charset_from(Charset::Cyrillic)
+///     .charset_into(Charset::Latin)
+///     .force_links(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(t.process("Ђурђевак").unwrap(), "Đurđevak");
+/// ```
+#[derive(Clone)]
+pub struct TransliteratorBuilder {
+    charset_from: Charset,
+    charset_into: Charset,
+    skip_digraph: bool,
+    force_foreign: bool,
+    force_links: bool,
+    exception_lists: Vec<ExceptionList>,
+    prefix_rules: bool,
+    protected_terms: Vec<ProtectedTerms>,
+    protected_ignore_case: bool,
+    protected_suffixes: bool,
+    detect_from: bool,
+    mixed_scripts: bool,
+    repair_homoglyphs: bool,
+    round_trip: bool,
+    ignorable: Vec<RangeInclusive<char>>,
+    normalization: Normalization,
+    accents: bool,
+    foreign_suffixes: bool,
+}
+
+impl Default for TransliteratorBuilder {
+    fn default() -> Self {
+        Self {
+            charset_from: Charset::Latin,
+            charset_into: Charset::Cyrillic,
+            skip_digraph: false,
+            force_foreign: false,
+            force_links: false,
+            exception_lists: Vec::new(),
+            prefix_rules: false,
+            protected_terms: Vec::new(),
+            protected_ignore_case: false,
+            protected_suffixes: false,
+            detect_from: false,
+            mixed_scripts: false,
+            repair_homoglyphs: false,
+            round_trip: false,
+            ignorable: charmaps::IGNORABLE.to_vec(),
+            normalization: Normalization::Keep,
+            accents: false,
+            foreign_suffixes: false,
+        }
+    }
+}
+
+impl TransliteratorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Transliterate words from this charset
+    pub fn charset_from(mut self, charset: Charset) -> Self {
+        self.charset_from = charset;
+        self
+    }
+
+    /// Transliterate words into this charset
+    pub fn charset_into(mut self, charset: Charset) -> Self {
+        self.charset_into = charset;
+        self
+    }
+
+    /// Skip checking for digraph exceptions
+    pub fn skip_digraph(mut self, skip_digraph: bool) -> Self {
+        self.skip_digraph = skip_digraph;
+        self
+    }
+
+    /// Force transliterate foreign words (ie. words that contain
+    /// characters not found in source charset)
+    pub fn force_foreign(mut self, force_foreign: bool) -> Self {
+        self.force_foreign = force_foreign;
+        self
+    }
+
+    /// Force transliterate URLs, email addresses and units
+    pub fn force_links(mut self, force_links: bool) -> Self {
+        self.force_links = force_links;
+        self
+    }
+
+    /// Merge user-supplied digraph exceptions with built-in ones.
+    /// Lists are applied in the order they were added.
+    pub fn exceptions(mut self, list: ExceptionList) -> Self {
+        self.exception_lists.push(list);
+        self
+    }
+
+    /// Split digraphs after prefixes such as "pod" in "podžupan" when
+    /// the stem starts with one of the expected letters, in words which
+    /// no digraph exception covers
+    pub fn prefix_rules(mut self, prefix_rules: bool) -> Self {
+        self.prefix_rules = prefix_rules;
+        self
+    }
+
+    /// Keep listed words untouched in both directions, before any
+    /// charset matching. Lists are merged in the order they were added.
+    pub fn protected_terms(mut self, terms: ProtectedTerms) -> Self {
+        self.protected_terms.push(terms);
+        self
+    }
+
+    /// Match protected terms regardless of letter case
+    pub fn protected_ignore_case(mut self, ignore_case: bool) -> Self {
+        self.protected_ignore_case = ignore_case;
+        self
+    }
+
+    /// Match protected terms followed by a hyphen or an apostrophe and
+    /// a case suffix, such as Google-u
+    pub fn protected_suffixes(mut self, suffixes: bool) -> Self {
+        self.protected_suffixes = suffixes;
+        self
+    }
+
+    /// Detect source charset from a sample of text passed to
+    /// [`Transliterator::detect`], which overrides `charset_from`
+    pub fn detect_from(mut self, detect_from: bool) -> Self {
+        self.detect_from = detect_from;
+        self
+    }
+
+    /// Mark letters which are not a digraph, such as "nj" written for
+    /// Cyrillic "нј", with a zero width non-joiner. When transliterating
+    /// from Latin, marked letters are kept separate while all unmarked
+    /// digraphs are converted, without checking digraph exceptions.
+    pub fn round_trip(mut self, round_trip: bool) -> Self {
+        self.round_trip = round_trip;
+        self
+    }
+
+    /// Carry these characters through to the output, ignoring them when
+    /// matching letters and digraph exceptions. By default, these are
+    /// soft hyphen, zero width joiner, word joiner and combining marks.
+    pub fn ignorable(mut self, characters: Vec<RangeInclusive<char>>) -> Self {
+        self.ignorable = characters;
+        self
+    }
+
+    /// Read accented vowels and syllabic r, such as "ȅ" in "rȅka", as
+    /// their base letter followed by the accent, which is carried over
+    /// as a combining mark. Otherwise precomposed accented letters are
+    /// foreign.
+    pub fn accents(mut self, accents: bool) -> Self {
+        self.accents = accents;
+        self
+    }
+
+    /// Keep Latin words followed by a hyphen or an apostrophe and a case
+    /// suffix (eg. Facebook-u) as is, while transliterating the suffix.
+    /// Cyrillic words are converted whole.
+    pub fn foreign_suffixes(mut self, foreign_suffixes: bool) -> Self {
+        self.foreign_suffixes = foreign_suffixes;
+        self
+    }
+
+    /// Write output in this Unicode normal form. Decomposed letters are
+    /// matched regardless of it.
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Transliterate each word from the script it is written in, so
+    /// both Latin and Cyrillic words are converted into `charset_into`
+    pub fn mixed_scripts(mut self, mixed_scripts: bool) -> Self {
+        self.mixed_scripts = mixed_scripts;
+        self
+    }
+
+    /// Replace letters which look like letters of the other script
+    /// (eg. Latin "a" in "Јавор") to match the rest of the word. URLs,
+    /// email addresses and units are only repaired with `force_links`.
+    pub fn repair_homoglyphs(mut self, repair_homoglyphs: bool) -> Self {
+        self.repair_homoglyphs = repair_homoglyphs;
+        self
+    }
+
+    /// Returns transliterator with selected options.
+    /// Fails if selected charsets can't be paired.
+    pub fn build(&self) -> Result<Transliterator, Error> {
+        // Detected charsets can't be paired with custom charmaps
+        if (self.detect_from || self.mixed_scripts)
+            && matches!(self.charset_into, Charset::Custom(_))
+        {
+            return Err(Error::UnpairedCharmap);
+        }
+        let mut transliterator = self.build_tables()?;
+        if self.round_trip {
+            let reverse = self
+                .clone()
+                .charset_from(self.charset_into.clone())
+                .charset_into(self.charset_from.clone())
+                .build_tables()?;
+            transliterator.joined = digraphs(&reverse.charset_from);
+            transliterator.digraphs = digraphs(&transliterator.charset_from);
+        }
+        if self.mixed_scripts {
+            for charset in [Charset::Latin, Charset::LatinUnicode, Charset::Cyrillic] {
+                let script = self
+                    .clone()
+                    .charset_from(charset.clone())
+                    .detect_from(false)
+                    .mixed_scripts(false)
+                    .build()?;
+                transliterator.scripts.push((charset, script));
+            }
+        }
+        Ok(transliterator)
+    }
+
+    fn build_tables(&self) -> Result<Transliterator, Error> {
+        let (f, i, e) = match (&self.charset_from, &self.charset_into) {
+            (Charset::Custom(from), Charset::Custom(into)) if Arc::ptr_eq(from, into) => {
+                let (f, i) = from.tables();
+                if f.len() != i.len() {
+                    return Err(Error::CharmapMismatch(f.len(), i.len()));
+                }
+                return self.assemble(
+                    Cow::Owned(f.to_vec()),
+                    Cow::Owned(i.to_vec()),
+                    from.exceptions().to_vec(),
+                );
+            }
+            (Charset::Custom(_), _) | (_, Charset::Custom(_)) => {
+                return Err(Error::UnpairedCharmap);
+            }
+            (Charset::Latin, Charset::Latin) => (charmaps::EMPTY, charmaps::EMPTY, false),
+            (Charset::LatinUnicode, Charset::LatinUnicode) => {
+                (charmaps::EMPTY, charmaps::EMPTY, false)
+            }
+            (Charset::Cyrillic, Charset::Cyrillic) => (charmaps::EMPTY, charmaps::EMPTY, false),
+            //
+            (Charset::Latin, Charset::LatinUnicode) => {
+                (charmaps::LATIN_DIRTY, charmaps::LATIN_DIRTY_UNICODE, true)
+            }
+            (Charset::LatinUnicode, Charset::Latin) => {
+                (charmaps::LATIN_CLEAN_UNICODE, charmaps::LATIN_CLEAN, false)
+            }
+            //
+            (Charset::Latin, Charset::Cyrillic) => {
+                (charmaps::LATIN_DIRTY, charmaps::CYRILLIC_DIRTY, true)
+            }
+            (Charset::LatinUnicode, Charset::Cyrillic) => {
+                (charmaps::LATIN_CLEAN_UNICODE, charmaps::CYRILLIC_CLEAN, true)
+            }
+            //
+            (Charset::Cyrillic, Charset::Latin) => {
+                (charmaps::CYRILLIC_CLEAN, charmaps::LATIN_CLEAN, false)
+            }
+            (Charset::Cyrillic, Charset::LatinUnicode) => {
+                (charmaps::CYRILLIC_CLEAN, charmaps::LATIN_CLEAN_UNICODE, false)
+            }
+        };
+        if f.len() != i.len() {
+            return Err(Error::CharmapMismatch(f.len(), i.len()));
+        }
+        // If transliterating to latin8, transliterate exception too
+        let latinize = i == charmaps::LATIN_CLEAN_UNICODE || i == charmaps::LATIN_DIRTY_UNICODE;
+        let exceptions = match e {
+            true => ExceptionGroup::builtin(latinize),
+            false => Vec::new(),
+        };
+        self.assemble(Cow::Borrowed(f), Cow::Borrowed(i), exceptions)
+    }
+
+    fn assemble(
+        &self,
+        from: Cow<'static, [charmaps::Character<'static>]>,
+        into: Cow<'static, [charmaps::Character<'static>]>,
+        mut exceptions: Vec<ExceptionGroup>,
+    ) -> Result<Transliterator, Error> {
+        // Exception lists don't apply if charsets have no digraphs
+        if !exceptions.is_empty() {
+            for list in &self.exception_lists {
+                list.apply(&mut exceptions)?;
+            }
+        }
+        Ok(Transliterator {
+            matcher: CharmapTrie::new(&from),
+            charset_from: from,
+            charset_into: into,
+            exceptions: !exceptions.is_empty(),
+            exception_matcher: ExceptionMatcher::new(exceptions)?.prefix_rules(self.prefix_rules),
+            protected: TermMatcher::new(
+                &self.protected_terms,
+                self.protected_ignore_case,
+                self.protected_suffixes,
+            ),
+            repair_homoglyphs: self.repair_homoglyphs,
+            round_trip: self.round_trip,
+            joined: Vec::new(),
+            digraphs: Vec::new(),
+            ignorable: self.ignorable.clone(),
+            normalization: self.normalization,
+            accents: self.accents,
+            foreign_suffixes: self.foreign_suffixes,
+            repaired: AtomicUsize::new(0),
+            scripts: Vec::new(),
+            options: match self.detect_from {
+                true => Some(self.clone()),
+                false => None,
+            },
+            skip_digraph: self.skip_digraph,
+            force_foreign: self.force_foreign,
+            force_links: self.force_links,
+        })
+    }
+}
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
 #[allow(dead_code)]
 pub const SEPARATORS: &[char] = &[
     '\u{0020}', '\u{00A0}', '\u{1680}', '\u{180E}', '\u{2000}', '\u{2001}', '\u{2002}', '\u{2003}',
@@ -5,35 +8,35 @@ pub const SEPARATORS: &[char] = &[
     '\u{202F}', '\u{205F}', '\u{3000}', '\u{FEFF}',
 ];
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Case {
     Upper,
     Mixed,
     Lower,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Character<'a> {
-    pub value: &'a [char],
+    pub value: Cow<'a, [char]>,
     pub case: Case,
 }
 
 macro_rules! char {
     ($($c:literal),+, upper) => {
         Character {
-            value: &[$($c,)+],
+            value: Cow::Borrowed(&[$($c,)+]),
             case: Case::Upper,
         }
     };
     ($($c:literal),+, mixed) => {
         Character {
-            value: &[$($c,)+],
+            value: Cow::Borrowed(&[$($c,)+]),
             case: Case::Mixed,
         }
     };
     ($($c:literal),+, lower) => {
         Character {
-            value: &[$($c,)+],
+            value: Cow::Borrowed(&[$($c,)+]),
             case: Case::Lower,
         }
     };
@@ -531,14 +534,22 @@ pub const CYRILLIC_DIRTY: &[Character] = &[
 ];
 
 pub struct DigraphException<'a> {
+    pub name: &'a str,
     pub latin: &'a [Character<'a>],
     pub latinized: &'a [Character<'a>],
     pub cyrillic: &'a [Character<'a>],
     pub exceptions: &'a [&'a str],
+    /// Prefixes after which the digraph is split if the stem starts
+    /// with one of the letters, and which ambiguous digraphs follow
+    pub prefixes: &'a [&'a str],
+    pub stem: &'a str,
+    /// Starts of words with a true digraph after one of the prefixes
+    pub deny: &'a [&'a str],
 }
 
 pub const DIGRAPH_EXCEPTIONS: &[DigraphException<'static>] = &[
     DigraphException {
+        name: "dj",
         latin: &[
             char!['Đ', upper],
             char!['Ð', upper],
@@ -564,8 +575,12 @@ pub const DIGRAPH_EXCEPTIONS: &[DigraphException<'static>] = &[
             char!['д', 'ј', lower],
         ],
         exceptions: DIGRAPH_EXCEPTIONS_DJ,
+        prefixes: DIGRAPH_RULE_PREFIXES,
+        stem: "aeiou",
+        deny: &["nadja", "predjašnj"],
     },
     DigraphException {
+        name: "dž",
         latin: &[
             char!['D', 'Ž', upper],
             char!['Ǆ', upper],
@@ -597,8 +612,12 @@ pub const DIGRAPH_EXCEPTIONS: &[DigraphException<'static>] = &[
             char!['д', 'ж', lower],
         ],
         exceptions: DIGRAPH_EXCEPTIONS_DZ,
+        prefixes: DIGRAPH_RULE_PREFIXES,
+        stem: "aeioubdglmnrv",
+        deny: &["nadžak", "odžak", "odžac", "odžač"],
     },
     DigraphException {
+        name: "nj",
         latin: &[
             char!['N', 'J', upper],
             char!['Ǌ', upper],
@@ -624,6 +643,9 @@ pub const DIGRAPH_EXCEPTIONS: &[DigraphException<'static>] = &[
             char!['н', 'ј', lower],
         ],
         exceptions: DIGRAPH_EXCEPTIONS_NJ,
+        prefixes: &[],
+        stem: "",
+        deny: &[],
     },
 ];
 
@@ -648,7 +670,7 @@ pub const DIGRAPH_EXCEPTIONS_DJ: &[&str] = &[
     "dvonedje",
     "devetonedje",
     "desetonedje",
-    "djb",
+    "^djb",
     "djeva",
     "djevi",
     "djevo",
@@ -892,7 +914,156 @@ pub const DIGRAPH_EXCEPTIONS_NJ: &[&str] = &[
     "konjunk",
     "nekonjug",
     "nekonjunk",
-    "ssrnj",
+    "^ssrnj",
     "tanjug",
     "vanjezičk",
 ];
+
+// Zero width non-joiner, which marks letters that are not a digraph
+pub const DIGRAPH_BREAK: char = '\u{200C}';
+
+// Soft hyphen, joiners and combining marks, which are carried through to
+// the output but don't take part in matching letters
+pub const IGNORABLE: &[RangeInclusive<char>] = &[
+    '\u{00AD}'..='\u{00AD}',
+    '\u{0300}'..='\u{036F}',
+    '\u{1AB0}'..='\u{1AFF}',
+    '\u{1DC0}'..='\u{1DFF}',
+    '\u{200D}'..='\u{200D}',
+    '\u{2060}'..='\u{2060}',
+    '\u{20D0}'..='\u{20FF}',
+    '\u{FE20}'..='\u{FE2F}',
+];
+
+// Grave, acute, macron, double grave and inverted breve, which mark stress
+// and length of vowels and syllabic r
+pub const ACCENTS: &[char] = &['\u{0300}', '\u{0301}', '\u{0304}', '\u{030F}', '\u{0311}'];
+
+// Vowels and r of both scripts, which take accents
+pub const ACCENTED: &[char] = &[
+    'a', 'e', 'i', 'o', 'u', 'r', 'A', 'E', 'I', 'O', 'U', 'R', 'а', 'е', 'и', 'о', 'у', 'р', 'А',
+    'Е', 'И', 'О', 'У', 'Р',
+];
+
+// Latin digraphs and Cyrillic letters which are written as digraphs in Latin
+pub const DIGRAPH_LETTERS: &[&str] = &["dj", "dž", "nj", "đ", "ǆ", "ǌ", "ђ", "џ", "њ"];
+
+// Prefixes ending with "d", which is kept apart from "j" or "ž" of the stem
+pub const DIGRAPH_RULE_PREFIXES: &[&str] = &["nad", "od", "pod", "pred"];
+
+// Latin roots ending with the first letter of a digraph, after which
+// the digraph is likely to be two separate letters, in addition to
+// prefixes of the digraph exception rules
+pub const DIGRAPH_ROOTS: &[(&str, &str)] = &[("in", "nj")];
+
+// Latin and Cyrillic letters which look the same, and are often typed
+// in a word of the other script
+pub const HOMOGLYPHS: &[(char, char)] = &[
+    ('A', 'А'),
+    ('B', 'В'),
+    ('C', 'С'),
+    ('E', 'Е'),
+    ('H', 'Н'),
+    ('J', 'Ј'),
+    ('K', 'К'),
+    ('M', 'М'),
+    ('O', 'О'),
+    ('P', 'Р'),
+    ('T', 'Т'),
+    ('X', 'Х'),
+    ('a', 'а'),
+    ('c', 'с'),
+    ('e', 'е'),
+    ('j', 'ј'),
+    ('o', 'о'),
+    ('p', 'р'),
+    ('x', 'х'),
+    ('y', 'у'),
+];
+
+// Acronyms and brand names written only with Serbian Latin letters,
+// which are kept untouched when protected terms are enabled. Names which
+// are usually written in Cyrillic as they are transliterated, such as
+// Tesla, are left out.
+pub const PROTECTED_TERMS: &[&str] = &[
+    "Adobe", "AMD", "BBC", "BMW", "CD", "CNN", "DVD", "EU", "FIFA", "Google", "GPS", "HD", "HP",
+    "HTML", "IBM", "iPad", "iPhone", "NASA", "NATO", "Nokia", "PC", "PDF", "SMS", "UEFA", "UNESCO",
+    "UNICEF", "USB",
+];
+
+// Inflectional endings which may follow a foreign word after a hyphen
+// or an apostrophe, such as Google-u or Google-om
+pub const CASE_SUFFIXES: &[&str] = &[
+    "a",
+    "e",
+    "i",
+    "o",
+    "u",
+    "om",
+    "em",
+    "ov",
+    "ev",
+    "ovi",
+    "evi",
+    "ova",
+    "eva",
+    "ove",
+    "eve",
+    "ovu",
+    "evu",
+    "ovo",
+    "ima",
+    "ama",
+    "ju",
+    "ja",
+    "je",
+    "ji",
+    "jem",
+    "jom",
+    "jima",
+    "ovima",
+    "ovom",
+    "evom",
+    "ovog",
+    "ovoj",
+    "ovih",
+    "ovim",
+    "ovoga",
+    "ovome",
+    "а",
+    "е",
+    "и",
+    "о",
+    "у",
+    "ом",
+    "ем",
+    "ов",
+    "ев",
+    "ови",
+    "еви",
+    "ова",
+    "ева",
+    "ове",
+    "еве",
+    "ову",
+    "еву",
+    "ово",
+    "има",
+    "ама",
+    "ју",
+    "ја",
+    "је",
+    "ји",
+    "јем",
+    "јом",
+    "јима",
+    "овима",
+    "овом",
+    "евом",
+    "овог",
+    "овој",
+    "ових",
+    "овим",
+    "овога",
+    "овоме",
+];
@@ -0,0 +1,160 @@
+use super::charmaps::{self, Character};
+use super::homoglyph::{homoglyph, is_cyrillic, letter_counts};
+use super::Transliterator;
+
+/// Letter written in a different script than the rest of its string
+#[derive(Clone, Debug, PartialEq)]
+pub struct Confusable {
+    /// Offending letter
+    pub letter: char,
+    /// Letters which replace it in the skeleton
+    pub replacement: String,
+    /// Byte index of the letter within the string
+    pub start: usize,
+    /// Byte index after the letter within the string
+    pub end: usize,
+}
+
+/// Letters of a string which mix Serbian Latin and Cyrillic scripts
+#[derive(Clone, Debug, PartialEq)]
+pub struct Confusables {
+    /// String is a domain or an email address, so each of its labels
+    /// is checked separately
+    pub address: bool,
+    /// Letters written in a minority script
+    pub confusables: Vec<Confusable>,
+    /// String with all letters converted into the script of their
+    /// label, using look-alike letters where available
+    pub skeleton: String,
+}
+
+impl Confusables {
+    /// Checks if string mixes scripts
+    pub fn is_mixed(&self) -> bool {
+        !self.confusables.is_empty()
+    }
+}
+
+/// Returns replacement for a letter of the other script, preferring a
+/// look-alike letter over transliteration
+fn replacement(c: char, into_cyrillic: bool) -> String {
+    if let Some(h) = homoglyph(c, into_cyrillic) {
+        return h.to_string();
+    }
+    let tables: &[(&[Character], &[Character])] = match into_cyrillic {
+        true => &[
+            (charmaps::LATIN_CLEAN, charmaps::CYRILLIC_CLEAN),
+            (charmaps::LATIN_CLEAN_UNICODE, charmaps::CYRILLIC_CLEAN),
+        ],
+        false => &[(charmaps::CYRILLIC_CLEAN, charmaps::LATIN_CLEAN)],
+    };
+    tables
+        .iter()
+        .find_map(|(from, into)| {
+            from.iter()
+                .position(|l| *l.value == [c])
+                .map(|i| into[i].value.iter().collect())
+        })
+        .unwrap_or_else(|| c.to_string())
+}
+
+/// Finds letters of a label which are not written in its dominant
+/// script, decided by letters without a look-alike and then by the
+/// majority of letters. Labels without a majority are Latin.
+fn check_label(label: &str, offset: usize, report: &mut Confusables) {
+    let (latin, cyrillic) = letter_counts(label);
+    let into_cyrillic = match (latin.1, cyrillic.1) {
+        (0, 0) => cyrillic.0 > latin.0,
+        (l, c) => c > l,
+    };
+    for (i, c) in label.char_indices() {
+        match is_cyrillic(c) {
+            Some(cyrillic) if cyrillic != into_cyrillic => {
+                let replacement = replacement(c, into_cyrillic);
+                report.skeleton.push_str(&replacement);
+                report.confusables.push(Confusable {
+                    letter: c,
+                    replacement,
+                    start: offset + i,
+                    end: offset + i + c.len_utf8(),
+                });
+            }
+            _ => report.skeleton.push(c),
+        }
+    }
+}
+
+/// Finds letters which mix Serbian Latin and Cyrillic scripts within a
+/// string, such as an identifier, a username or a domain. Labels of
+/// domains and email addresses are checked separately.
+///
+/// # Example
+///
+/// ```
+/// use translitrs::find_confusables;
+///
+/// // Domain contains Cyrillic "а"
+/// let found = find_confusables("pаypal.rs");
+/// assert!(found.is_mixed());
+/// assert_eq!(found.skeleton, "paypal.rs");
+/// assert_eq!((found.confusables[0].start, found.confusables[0].end), (1, 3));
+/// assert!(!find_confusables("пример.rs").is_mixed());
+/// ```
+pub fn find_confusables(text: &str) -> Confusables {
+    // Letters are masked, so addresses in either script are recognized
+    let masked = text
+        .chars()
+        .map(|c| if c.is_alphabetic() { 'a' } else { c })
+        .collect::<String>();
+    let address = Transliterator::address_pattern(&masked);
+    let mut report = Confusables {
+        address,
+        confusables: Vec::new(),
+        skeleton: String::with_capacity(text.len()),
+    };
+    let mut offset = 0;
+    if address {
+        for (i, separator) in text.match_indices(['.', '@']) {
+            check_label(&text[offset..i], offset, &mut report);
+            report.skeleton.push_str(separator);
+            offset = i + separator.len();
+        }
+    }
+    check_label(&text[offset..], offset, &mut report);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_confusables() {
+        for (text, skeleton, positions) in [
+            ("paypal.rs", "paypal.rs", vec![]),
+            ("pаypаl.rs", "paypal.rs", vec![(1, 3), (5, 7)]),
+            ("mаrko@primer.rs", "marko@primer.rs", vec![(1, 3)]),
+            ("пример.rs", "пример.rs", vec![]),
+            ("пpимep", "пример", vec![(2, 3), (7, 8), (8, 9)]),
+            ("petar_петровић", "ретар_петровић", vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]),
+            ("ljiljaнa", "ljiljana", vec![(6, 8)]),
+            ("љubav", "ljubav", vec![(0, 2)]),
+            ("", "", vec![]),
+        ] {
+            let found = find_confusables(text);
+            assert_eq!(found.skeleton, skeleton, "{}", text);
+            assert_eq!(
+                found
+                    .confusables
+                    .iter()
+                    .map(|c| (c.start, c.end))
+                    .collect::<Vec<_>>(),
+                positions,
+                "{}",
+                text
+            );
+        }
+        assert!(find_confusables("mаrko@primer.rs").address);
+        assert!(!find_confusables("пpимep").address);
+    }
+}
@@ -0,0 +1,404 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::Path;
+use std::{fmt, fs};
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use toml::Spanned;
+
+use super::charmaps::{Case, Character};
+use super::exceptions::{self, ExceptionGroup, PrefixRule};
+use super::Error;
+
+/// Paired charmap tables loaded at runtime
+///
+/// Tables are described in TOML or JSON with the following layout,
+/// where entry `case` is optional and inferred from the letters:
+///
+/// ```toml
+/// name = "example"
+/// from = [{ value = "Dj", case = "mixed" }, { value = "a" }]
+/// into = [{ value = "Ђ", case = "mixed" }, { value = "а" }]
+///
+/// [[exceptions]]
+/// name = "dj"
+/// from = [{ value = "Dj" }]
+/// into = [{ value = "Дј" }]
+/// words = ["adjektiv"]
+/// ```
+///
+/// Entries are matched in the same way as built-in charmaps: later
+/// entries take priority, while `mixed` case entries are skipped when
+/// followed by an uppercase letter.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use translitrs::{Charmap, Charset, Transliterator};
+///
+/// let charmap = Arc::new(Charmap::from_json(r#"{
+///     "from": [{ "value": "x" }, { "value": "ks" }],
+///     "into": [{ "value": "кс" }, { "value": "x" }]
+/// }"#).unwrap());
+/// let t = Transliterator::builder()
+///     .charset_from(Charset::Custom(charmap.clone()))
+///     .charset_into(Charset::Custom(charmap))
+///     .build()
+///     .unwrap();
+/// assert_eq!(t.process("xks").unwrap(), "ксx");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Charmap {
+    name: String,
+    from: Vec<Character<'static>>,
+    into: Vec<Character<'static>>,
+    exceptions: Vec<ExceptionGroup>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File<E> {
+    name: Option<String>,
+    from: Vec<E>,
+    into: Vec<E>,
+    #[serde(default = "Vec::new")]
+    exceptions: Vec<Group<E>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Group<E> {
+    name: String,
+    from: Vec<E>,
+    into: Vec<E>,
+    words: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    value: String,
+    case: Option<CaseName>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CaseName {
+    Upper,
+    Mixed,
+    Lower,
+}
+
+/// Charmap entry with line number of its definition
+struct Located {
+    line: usize,
+    entry: Entry,
+}
+
+/// Entry deserialized together with its position in the source text
+trait Locate {
+    fn locate(self, text: &str) -> Result<Located, Error>;
+}
+
+fn line_number(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+fn invalid<T, S: fmt::Display>(line: usize, message: S) -> Result<T, Error> {
+    Err(Error::Charmap(line, message.to_string()))
+}
+
+impl Locate for Spanned<Entry> {
+    fn locate(self, text: &str) -> Result<Located, Error> {
+        Ok(Located {
+            line: line_number(text, self.span().start),
+            entry: self.into_inner(),
+        })
+    }
+}
+
+impl Locate for &RawValue {
+    fn locate(self, text: &str) -> Result<Located, Error> {
+        // Raw value borrows from the text, so its offset is known
+        let offset = self.get().as_ptr() as usize - text.as_ptr() as usize;
+        let line = line_number(text, offset);
+        match serde_json::from_str(self.get()) {
+            Ok(entry) => Ok(Located { line, entry }),
+            Err(e) => invalid(line + e.line() - 1, e),
+        }
+    }
+}
+
+impl Located {
+    fn into_character(self) -> Result<Character<'static>, Error> {
+        let value = self.entry.value.chars().collect::<Vec<char>>();
+        if value.is_empty() {
+            return invalid(self.line, "empty character value");
+        }
+        let case = match self.entry.case {
+            Some(CaseName::Upper) => Case::Upper,
+            Some(CaseName::Mixed) => Case::Mixed,
+            Some(CaseName::Lower) => Case::Lower,
+            None if value.len() > 1 && value[0].is_uppercase() && value[1].is_lowercase() => {
+                Case::Mixed
+            }
+            None if value[0].is_uppercase() => Case::Upper,
+            None => Case::Lower,
+        };
+        Ok(Character {
+            value: Cow::Owned(value),
+            case,
+        })
+    }
+}
+
+/// Validates a pair of tables, returning their located entries
+fn pair_tables<E: Locate>(
+    text: &str,
+    from: Vec<E>,
+    into: Vec<E>,
+) -> Result<(Vec<Located>, Vec<Located>), Error> {
+    let from = from
+        .into_iter()
+        .map(|e| e.locate(text))
+        .collect::<Result<Vec<_>, _>>()?;
+    let into = into
+        .into_iter()
+        .map(|e| e.locate(text))
+        .collect::<Result<Vec<_>, _>>()?;
+    if from.len() > into.len() {
+        let e = &from[into.len()];
+        return invalid(e.line, format!("source \"{}\" has no target pair", e.entry.value));
+    }
+    if into.len() > from.len() {
+        let e = &into[from.len()];
+        return invalid(e.line, format!("target \"{}\" has no source pair", e.entry.value));
+    }
+    let mut sources = HashSet::new();
+    for e in &from {
+        if !sources.insert(e.entry.value.as_str()) {
+            return invalid(e.line, format!("duplicate source \"{}\"", e.entry.value));
+        }
+    }
+    Ok((from, into))
+}
+
+fn characters(table: Vec<Located>) -> Result<Vec<Character<'static>>, Error> {
+    table.into_iter().map(Located::into_character).collect()
+}
+
+impl Charmap {
+    fn from_file<E: Locate>(text: &str, file: File<E>) -> Result<Self, Error> {
+        let (from, into) = pair_tables(text, file.from, file.into)?;
+        let mut exceptions = Vec::with_capacity(file.exceptions.len());
+        for group in file.exceptions {
+            let (source, target) = pair_tables(text, group.from, group.into)?;
+            for e in &source {
+                if !from.iter().any(|f| f.entry.value == e.entry.value) {
+                    return invalid(
+                        e.line,
+                        format!("exception \"{}\" is not a source character", e.entry.value),
+                    );
+                }
+            }
+            // Words aren't located, so errors point to the group sources
+            let line = source.first().map_or(1, |e| e.line);
+            for word in &group.words {
+                if let Err(e) = exceptions::validate(word) {
+                    return invalid(line, format!("exception \"{}\" - {}", word, e));
+                }
+            }
+            exceptions.push(ExceptionGroup {
+                name: group.name,
+                source: characters(source)?,
+                target: characters(target)?,
+                exceptions: group
+                    .words
+                    .iter()
+                    .map(|w| exceptions::normalize(w))
+                    .collect(),
+                rule: PrefixRule::default(),
+            });
+        }
+        Ok(Self {
+            name: file.name.unwrap_or_default(),
+            from: characters(from)?,
+            into: characters(into)?,
+            exceptions,
+        })
+    }
+
+    /// Parses charmap tables from TOML
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        let file: File<Spanned<Entry>> = match toml::from_str(text) {
+            Ok(file) => file,
+            Err(e) => {
+                let line = e.span().map(|s| line_number(text, s.start)).unwrap_or(0);
+                return invalid(line, e.message());
+            }
+        };
+        Self::from_file(text, file)
+    }
+
+    /// Parses charmap tables from JSON
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let file: File<&RawValue> = match serde_json::from_str(text) {
+            Ok(file) => file,
+            Err(e) => return invalid(e.line(), e),
+        };
+        Self::from_file(text, file)
+    }
+
+    /// Loads charmap tables from a file, parsing it as JSON if it has
+    /// `.json` extension and as TOML otherwise
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        match path.extension() {
+            Some(e) if e.eq_ignore_ascii_case("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    /// Returns name of the charmap
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn tables(&self) -> (&[Character<'static>], &[Character<'static>]) {
+        (&self.from, &self.into)
+    }
+
+    pub(super) fn exceptions(&self) -> &[ExceptionGroup] {
+        &self.exceptions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transliterate::{Charset, Transliterator};
+
+    const TOML: &str = r#"
+name = "test"
+from = [
+    { value = "a" },
+    { value = "Nj", case = "mixed" },
+    { value = "nj" },
+]
+into = [
+    { value = "а" },
+    { value = "Њ", case = "mixed" },
+    { value = "њ" },
+]
+
+[[exceptions]]
+name = "nj"
+from = [{ value = "nj" }]
+into = [{ value = "нј" }]
+words = ["Konjug"]
+"#;
+
+    fn error_line(result: Result<Charmap, Error>) -> usize {
+        match result {
+            Err(Error::Charmap(line, _)) => line,
+            _ => panic!("charmap should be invalid"),
+        }
+    }
+
+    #[test]
+    fn test_parse() -> Result<(), Error> {
+        let charmap = Charmap::from_toml(TOML)?;
+        assert_eq!(charmap.name(), "test");
+        assert_eq!(charmap.from.len(), 3);
+        assert_eq!(charmap.from[1].case, Case::Mixed);
+        assert_eq!(charmap.from[2].case, Case::Lower);
+        assert_eq!(charmap.exceptions[0].exceptions, vec!["konjug"]);
+        let charmap = Charmap::from_json(
+            r#"{"from": [{"value": "A"}, {"value": "Lj"}], "into": [{"value": "А"}, {"value": "Љ"}]}"#,
+        )?;
+        assert_eq!(charmap.from[0].case, Case::Upper);
+        assert_eq!(charmap.from[1].case, Case::Mixed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transliterate() -> Result<(), Error> {
+        let charmap = Arc::new(Charmap::from_toml(TOML)?);
+        let t = Transliterator::builder()
+            .charset_from(Charset::Custom(charmap.clone()))
+            .charset_into(Charset::Custom(charmap.clone()))
+            .force_foreign(true)
+            .build()?;
+        assert_eq!(t.process("Nja NJA nja konjug")?, "Ња NJA ња koнјug");
+        assert!(matches!(
+            Transliterator::builder()
+                .charset_from(Charset::Custom(charmap))
+                .build(),
+            Err(Error::UnpairedCharmap)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "TransliteratorBuilder::build")]
+    fn test_new_custom() {
+        let charmap = Arc::new(Charmap::from_toml(TOML).unwrap());
+        Transliterator::new(Charset::Custom(charmap), Charset::Latin, false, false, false);
+    }
+
+    #[test]
+    fn test_single_letter_exception() -> Result<(), Error> {
+        // Exception value is longer than its source, so only the source
+        // letter is consumed
+        let charmap = Arc::new(Charmap::from_toml(
+            r#"
+from = [{ value = "a" }, { value = "đ" }, { value = "k" }]
+into = [{ value = "а" }, { value = "ђ" }, { value = "к" }]
+
+[[exceptions]]
+name = "đ"
+from = [{ value = "đ" }]
+into = [{ value = "дј" }]
+words = ["ađak"]
+"#,
+        )?);
+        let t = Transliterator::builder()
+            .charset_from(Charset::Custom(charmap.clone()))
+            .charset_into(Charset::Custom(charmap))
+            .build()?;
+        assert_eq!(t.process("ađak đak")?, "адјак ђак");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        // Mismatched lengths
+        let toml = TOML.replace("    { value = \"њ\" },\n", "");
+        assert_eq!(error_line(Charmap::from_toml(&toml)), 6);
+        let json = "{\n\"from\": [{\"value\": \"a\"}],\n\"into\": [\n{\"value\": \"а\"},\n{\"value\": \"б\"}\n]\n}";
+        assert_eq!(error_line(Charmap::from_json(json)), 5);
+        // Duplicate sources
+        let toml = TOML.replace("{ value = \"nj\" },", "{ value = \"a\" },");
+        assert_eq!(error_line(Charmap::from_toml(&toml)), 6);
+        let json = "{\n\"from\": [\n{\"value\": \"a\"},\n{\"value\": \"a\"}\n],\n\"into\": [{\"value\": \"а\"}, {\"value\": \"б\"}]\n}";
+        assert_eq!(error_line(Charmap::from_json(json)), 4);
+        // Exceptions for unknown characters
+        let toml = TOML.replace("from = [{ value = \"nj\" }]", "from = [{ value = \"dj\" }]");
+        assert_eq!(error_line(Charmap::from_toml(&toml)), 16);
+        // Malformed entries
+        let toml = TOML.replace(
+            "{ value = \"Nj\", case = \"mixed\" }",
+            "{ value = \"Nj\", case = \"title\" }",
+        );
+        assert_eq!(error_line(Charmap::from_toml(&toml)), 5);
+        let json = "{\n\"from\": [\n{\"value\": \"\"}\n],\n\"into\": [{\"value\": \"а\"}]\n}";
+        assert_eq!(error_line(Charmap::from_json(json)), 3);
+        let json = "{\n\"from\": [],\n\"into\": [\n{\"value\": 1}\n]\n}";
+        assert_eq!(error_line(Charmap::from_json(json)), 4);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use lazy_static::lazy_static;
+
+use super::charmaps::{self, Character};
+use super::Charset;
+
+/// Detected source charset of a text
+#[derive(Clone, Debug)]
+pub struct Detection {
+    pub charset: Charset,
+    /// Share of letters written in the detected charset, between 0 and 1
+    pub confidence: f32,
+}
+
+fn letters(charmap: &[Character]) -> HashSet<char> {
+    charmap
+        .iter()
+        .flat_map(|c| c.value.iter().copied())
+        .collect()
+}
+
+lazy_static! {
+    static ref LATIN: HashSet<char> = letters(charmaps::LATIN_CLEAN);
+    static ref LIGATURES: HashSet<char> = letters(charmaps::LATIN_CLEAN_UNICODE)
+        .difference(&LATIN)
+        .copied()
+        .collect();
+    static ref CYRILLIC: HashSet<char> = letters(charmaps::CYRILLIC_CLEAN);
+}
+
+/// Returns charset of a Serbian letter, where Latin Unicode is used only
+/// for digraph ligatures
+pub(super) fn letter_charset(c: char) -> Option<Charset> {
+    if LATIN.contains(&c) {
+        Some(Charset::Latin)
+    } else if LIGATURES.contains(&c) {
+        Some(Charset::LatinUnicode)
+    } else if CYRILLIC.contains(&c) {
+        Some(Charset::Cyrillic)
+    } else {
+        None
+    }
+}
+
+/// Checks if two letters form a digraph written with ASCII letters
+fn ascii_digraph(first: char, second: char) -> bool {
+    matches!(
+        (first.to_ascii_lowercase(), second),
+        ('d' | 'l' | 'n', 'j' | 'J') | ('d', 'ž' | 'Ž')
+    )
+}
+
+/// Detects script of a text from distribution of its letters. Text is
+/// in Latin Unicode if it contains digraph ligatures and no digraphs
+/// written with two letters, while letters found in neither charset are
+/// ignored.
+///
+/// # Example
+///
+/// ```
+/// use translitrs::{detect_charset, Charset};
+///
+/// let detection = detect_charset("Ово је ћирилица, a ovo nije.");
+/// assert!(matches!(detection.charset, Charset::Cyrillic));
+/// assert!(detection.confidence > 0.5);
+/// assert!(matches!(detect_charset("ǈubičica").charset, Charset::LatinUnicode));
+/// ```
+pub fn detect_charset(text: &str) -> Detection {
+    let (mut latin, mut ligatures, mut cyrillic) = (0, 0, 0);
+    let mut digraphs = false;
+    let mut previous = ' ';
+    for c in text.chars() {
+        digraphs |= ascii_digraph(previous, c);
+        previous = c;
+        match letter_charset(c) {
+            Some(Charset::Latin) => latin += 1,
+            Some(Charset::LatinUnicode) => ligatures += 1,
+            Some(_) => cyrillic += 1,
+            None => {}
+        }
+    }
+    let total = latin + ligatures + cyrillic;
+    let (charset, count) = if cyrillic > latin + ligatures {
+        (Charset::Cyrillic, cyrillic)
+    } else if ligatures > 0 && !digraphs {
+        (Charset::LatinUnicode, latin + ligatures)
+    } else {
+        (Charset::Latin, latin + ligatures)
+    };
+    Detection {
+        charset,
+        confidence: match total {
+            0 => 0.0,
+            _ => count as f32 / total as f32,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        for (text, expected, confidence) in [
+            ("", Charset::Latin, 0.0),
+            ("123 ?! 例子", Charset::Latin, 0.0),
+            ("Ljubičica", Charset::Latin, 1.0),
+            ("ǇUBIČICA", Charset::LatinUnicode, 1.0),
+            ("Љубичица", Charset::Cyrillic, 1.0),
+            ("Ово је USB", Charset::Cyrillic, 0.625),
+            ("Ovo je ćirilica: ћ", Charset::Latin, 13.0 / 14.0),
+            // Latin charset reads both ligatures and digraphs
+            ("ljubav, ǉubav", Charset::Latin, 1.0),
+            ("ǈubav, ǌiva, ǆak", Charset::LatinUnicode, 1.0),
+        ] {
+            let detection = detect_charset(text);
+            assert_eq!(detection.charset, expected, "{}", text);
+            assert!((detection.confidence - confidence).abs() < 1e-6, "{}", text);
+        }
+    }
+}
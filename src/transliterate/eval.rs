@@ -0,0 +1,211 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::charmaps;
+use super::explain::{WordAction, WordReport};
+
+/// Likely cause of a word transliterated differently than expected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MismatchCause {
+    /// Word contains a digraph, or letters which are written as one
+    DigraphException,
+    /// Word has been skipped for a letter not found in source charset
+    ForeignSkip,
+    /// Word has been skipped as a URL, an email address or a unit
+    LinkSkip,
+    /// Output differs from expected word only in case
+    Case,
+    /// Line of the input has a different number of words than the
+    /// expected line, so its words can't be compared
+    Misaligned,
+    /// Word has been transliterated differently for any other reason
+    Other,
+}
+
+/// Word transliterated differently than expected
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Mismatch {
+    /// Line of the input containing the word, starting from 1
+    pub line: usize,
+    pub input: String,
+    pub expected: String,
+    pub output: String,
+    pub cause: MismatchCause,
+}
+
+/// Comparison of transliterated text with its expected transliteration
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Evaluation {
+    /// Number of compared words
+    pub words: usize,
+    /// Number of words transliterated as expected
+    pub correct: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Evaluation {
+    /// Returns share of words transliterated as expected
+    pub fn accuracy(&self) -> f64 {
+        match self.words {
+            0 => 1.0,
+            words => self.correct as f64 / words as f64,
+        }
+    }
+
+    /// Returns number of mismatches with a cause
+    pub fn count(&self, cause: MismatchCause) -> usize {
+        self.mismatches.iter().filter(|m| m.cause == cause).count()
+    }
+
+    pub(super) fn compare(&mut self, input: &str, report: WordReport, expected: &str) {
+        self.words += 1;
+        if report.output == expected {
+            self.correct += 1;
+            return;
+        }
+        let has_digraph = |word: &str| {
+            let word = word.to_lowercase();
+            charmaps::DIGRAPH_LETTERS.iter().any(|d| word.contains(d))
+        };
+        let cause = match report.action {
+            WordAction::LinkSkipped => MismatchCause::LinkSkip,
+            WordAction::ForeignSkipped { .. } => MismatchCause::ForeignSkip,
+            _ if report.output.to_lowercase() == expected.to_lowercase() => MismatchCause::Case,
+            WordAction::ExceptionApplied { .. } => MismatchCause::DigraphException,
+            _ if has_digraph(&report.input) || has_digraph(expected) => {
+                MismatchCause::DigraphException
+            }
+            _ => MismatchCause::Other,
+        };
+        self.mismatches.push(Mismatch {
+            line: input[..report.start].matches('\n').count() + 1,
+            input: report.input,
+            expected: expected.to_owned(),
+            output: report.output,
+            cause,
+        });
+    }
+
+    /// Records a whole line which can't be compared word by word, with
+    /// its words counted as mismatched. Reports start at `offset`, the
+    /// position of the line in the input.
+    pub(super) fn misaligned(
+        &mut self,
+        line: usize,
+        offset: usize,
+        input: &str,
+        reports: Vec<WordReport>,
+        expected: &str,
+    ) {
+        self.words += reports.len();
+        let mut output = String::with_capacity(input.len());
+        let mut cursor = 0;
+        for report in reports {
+            let start = report.start - offset;
+            output.push_str(&input[cursor..start]);
+            output.push_str(&report.output);
+            cursor = start + report.input.len();
+        }
+        output.push_str(&input[cursor..]);
+        self.mismatches.push(Mismatch {
+            line,
+            input: input.trim().to_owned(),
+            expected: expected.trim().to_owned(),
+            output: output.trim().to_owned(),
+            cause: MismatchCause::Misaligned,
+        });
+    }
+}
+
+impl fmt::Display for MismatchCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DigraphException => write!(f, "digraph exception"),
+            Self::ForeignSkip => write!(f, "foreign skip"),
+            Self::LinkSkip => write!(f, "link skip"),
+            Self::Case => write!(f, "case"),
+            Self::Misaligned => write!(f, "misaligned"),
+            Self::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.line, self.input, self.expected, self.output, self.cause
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate::{Charset, Error, Transliterator};
+
+    #[test]
+    fn test_evaluate() -> Result<(), Error> {
+        let t = Transliterator::default();
+        let evaluation = t.evaluate(
+            "Ljubav, podžanr\nexample.com Mária DŽEP nadjačan",
+            "Љубав, поджанр\nекампле.цом Марија ЏЕП надјачан",
+        )?;
+        assert_eq!((evaluation.words, evaluation.correct), (6, 4));
+        let found = evaluation
+            .mismatches
+            .iter()
+            .map(|m| (m.line, m.input.as_str(), m.cause))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (2, "example.com", MismatchCause::LinkSkip),
+                (2, "Mária", MismatchCause::ForeignSkip),
+            ]
+        );
+        let t = Transliterator::new(Charset::Cyrillic, Charset::Latin, false, false, false);
+        let evaluation = t.evaluate("Ђак и ЏЕП", "Djak i DžEP")?;
+        let causes = evaluation
+            .mismatches
+            .iter()
+            .map(|m| m.cause)
+            .collect::<Vec<_>>();
+        assert_eq!(causes, vec![MismatchCause::DigraphException, MismatchCause::Case]);
+        assert!((evaluation.accuracy() - 1.0 / 3.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_misaligned() -> Result<(), Error> {
+        let t = Transliterator::default();
+        let evaluation = t.evaluate(
+            "Njiva je tu.\nPolje i\tlivada, reka.\nKraj",
+            "Њива је ту.\nПоље, ливада, река.\nКрај\nВишак",
+        )?;
+        assert_eq!((evaluation.words, evaluation.correct), (8, 4));
+        assert_eq!(
+            evaluation.mismatches,
+            vec![
+                Mismatch {
+                    line: 2,
+                    input: "Polje i\tlivada, reka.".to_owned(),
+                    expected: "Поље, ливада, река.".to_owned(),
+                    output: "Поље и\tливада, река.".to_owned(),
+                    cause: MismatchCause::Misaligned,
+                },
+                Mismatch {
+                    line: 4,
+                    input: String::new(),
+                    expected: "Вишак".to_owned(),
+                    output: String::new(),
+                    cause: MismatchCause::Misaligned,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,658 @@
+use std::fs;
+use std::path::Path;
+
+use aho_corasick::AhoCorasick;
+use regex::Regex;
+
+use super::charmaps::{self, Character};
+use super::Error;
+
+/// Digraph exceptions with their replacements in the target charset
+#[derive(Clone, Debug)]
+pub struct ExceptionGroup {
+    pub name: String,
+    /// Digraphs in source charset
+    pub source: Vec<Character<'static>>,
+    /// Replacements for each of the digraphs
+    pub target: Vec<Character<'static>>,
+    /// Lowercase parts of words in which digraphs are replaced, in the
+    /// syntax described by [`ExceptionList`]
+    pub exceptions: Vec<String>,
+    pub rule: PrefixRule,
+}
+
+/// Prefixes after which a digraph is replaced, unless the word starts
+/// with one of the denied strings
+#[derive(Clone, Debug, Default)]
+pub struct PrefixRule {
+    pub prefixes: Vec<String>,
+    /// Letters which the stem may start with after the digraph
+    pub stem: Vec<char>,
+    /// Lowercase starts of words in which the digraph is kept
+    pub deny: Vec<String>,
+}
+
+impl ExceptionGroup {
+    /// Returns built-in exception groups, replacing digraphs with
+    /// Cyrillic letters, or with separated Latin letters when
+    /// `latinize` is set
+    pub fn builtin(latinize: bool) -> Vec<Self> {
+        charmaps::DIGRAPH_EXCEPTIONS
+            .iter()
+            .map(|e| Self {
+                name: e.name.to_owned(),
+                source: e.latin.to_vec(),
+                target: match latinize {
+                    true => e.latinized.to_vec(),
+                    false => e.cyrillic.to_vec(),
+                },
+                exceptions: e.exceptions.iter().map(|s| s.to_string()).collect(),
+                rule: PrefixRule {
+                    prefixes: e.prefixes.iter().map(|s| s.to_string()).collect(),
+                    stem: e.stem.chars().collect(),
+                    deny: e.deny.iter().map(|s| s.to_string()).collect(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// User-supplied digraph exceptions, merged with built-in groups
+///
+/// Lists are plain text files, where words are grouped under the
+/// digraph they apply to. Words prefixed with `!` suppress built-in
+/// exceptions, and `#` starts a comment.
+///
+/// Words match anywhere within a word, unless anchored to its start
+/// with `^` or to its end with `$`. Words enclosed in `/` are regular
+/// expressions matched against the lowercase word. An exception applies
+/// to all digraphs of its group within the word, or only to the one
+/// split by `|`, or by the end of the first capture group of a regular
+/// expression.
+///
+/// ```text
+/// [nj]
+/// injektor
+/// !tanjug  # suppress built-in exception
+/// ^anjon$  # whole word
+/// /^ko(n)jun/
+///
+/// [dž]
+/// nad|žnjeti
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use translitrs::{ExceptionList, Transliterator};
+///
+/// let list = ExceptionList::parse("[nj]\nkonjektur\n!konjug\n").unwrap();
+/// let t = Transliterator::builder().exceptions(list).build().unwrap();
+/// assert_eq!(t.process("konjektura konjugacija").unwrap(), "конјектура коњугација");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ExceptionList {
+    // Group name and word of each entry
+    additions: Vec<(String, String)>,
+    suppressions: Vec<(String, String)>,
+}
+
+impl ExceptionList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns exception group name, accepting ASCII spelling of "dž"
+    fn group_name(name: &str) -> String {
+        match name.trim().to_lowercase().as_str() {
+            "dz" => "dž".to_owned(),
+            name => name.to_owned(),
+        }
+    }
+
+    /// Parses exception list from text
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut list = Self::new();
+        let mut group: Option<String> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                group = Some(Self::group_name(name));
+                continue;
+            }
+            let group = match &group {
+                Some(group) => group,
+                None => {
+                    return Err(Error::ExceptionList(
+                        i + 1,
+                        "word is not preceded by a digraph group".to_owned(),
+                    ))
+                }
+            };
+            if line.contains(char::is_whitespace) {
+                return Err(Error::ExceptionList(i + 1, "exception contains spaces".to_owned()));
+            }
+            let (word, suppress) = match line.strip_prefix('!') {
+                Some(word) => (word, true),
+                None => (line, false),
+            };
+            validate(word).map_err(|message| Error::ExceptionList(i + 1, message))?;
+            match suppress {
+                true => list.suppress(group, word),
+                false => list.add(group, word),
+            }
+        }
+        Ok(list)
+    }
+
+    /// Loads exception list from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Adds exception to a digraph group
+    pub fn add(&mut self, group: &str, word: &str) {
+        self.additions
+            .push((Self::group_name(group), normalize(word)));
+    }
+
+    /// Removes exception from a digraph group
+    pub fn suppress(&mut self, group: &str, word: &str) {
+        self.suppressions
+            .push((Self::group_name(group), normalize(word)));
+    }
+
+    /// Merges exceptions into groups. Fails if list refers to a group
+    /// which doesn't exist.
+    pub(super) fn apply(&self, groups: &mut [ExceptionGroup]) -> Result<(), Error> {
+        fn find<'a>(
+            groups: &'a mut [ExceptionGroup],
+            name: &str,
+        ) -> Result<&'a mut ExceptionGroup, Error> {
+            groups
+                .iter_mut()
+                .find(|g| g.name == name)
+                .ok_or_else(|| Error::UnknownExceptionGroup(name.to_owned()))
+        }
+        for (name, word) in &self.suppressions {
+            let group = find(groups, name)?;
+            group.exceptions.retain(|e| e != word);
+            // Prefix rules don't apply to suppressed words either, nor
+            // are they reported as ambiguous
+            if let Ok(Pattern::Plain { text, .. }) = Pattern::parse(word) {
+                if !group.rule.deny.contains(&text) {
+                    group.rule.deny.push(text);
+                }
+            }
+        }
+        for (name, word) in &self.additions {
+            let group = find(groups, name)?;
+            if !group.exceptions.contains(word) {
+                group.exceptions.push(word.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exception string split into text matched by the automaton and its
+/// anchors, or a regular expression
+enum Pattern {
+    Plain {
+        text: String,
+        // Match has to start at the first letter of the word
+        start: bool,
+        // Match has to end at the last letter of the word
+        end: bool,
+        // Byte offset of the split between letters of the digraph
+        split: Option<usize>,
+    },
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn parse(exception: &str) -> Result<Self, String> {
+        if let Some(re) = exception
+            .strip_prefix('/')
+            .and_then(|e| e.strip_suffix('/'))
+        {
+            return Regex::new(re).map(Self::Regex).map_err(|e| e.to_string());
+        }
+        let (start, rest) = match exception.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, exception),
+        };
+        let (end, rest) = match rest.strip_suffix('$') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let split = rest.find('|');
+        let text = rest.replacen('|', "", 1);
+        if text.is_empty() {
+            return Err("exception is empty".to_owned());
+        }
+        if text.contains('|') {
+            return Err("exception contains more than one split marker".to_owned());
+        }
+        if split == Some(0) || split == Some(text.len()) {
+            return Err("split marker is not placed between two letters".to_owned());
+        }
+        Ok(Self::Plain {
+            text,
+            start,
+            end,
+            split,
+        })
+    }
+}
+
+/// Checks syntax of an exception string
+pub(super) fn validate(exception: &str) -> Result<(), String> {
+    Pattern::parse(exception).map(|_| ())
+}
+
+/// Returns exception string in the form it is matched with, keeping
+/// regular expressions intact
+pub(super) fn normalize(exception: &str) -> String {
+    match exception.starts_with('/') {
+        true => exception.to_owned(),
+        false => exception.to_lowercase(),
+    }
+}
+
+/// Exception string matched by the automaton
+struct PlainPattern {
+    group: usize,
+    exception: usize,
+    start: bool,
+    end: bool,
+    split: Option<usize>,
+}
+
+/// Digraph exceptions of all groups compiled into a single automaton
+///
+/// A word is scanned once for all exception strings, instead of
+/// searching for each of them separately whenever a digraph is found.
+/// Regular expressions are matched separately.
+pub struct ExceptionMatcher {
+    groups: Vec<ExceptionGroup>,
+    automaton: AhoCorasick,
+    patterns: Vec<PlainPattern>,
+    // Group index, exception index and expression of each regex
+    regexes: Vec<(usize, usize, Regex)>,
+    // Prefix rules of each group, with the prefix followed by the
+    // second letter of the digraph, the same separated by a hyphen as
+    // reported, and byte length of the prefix
+    rules: Vec<Vec<(String, String, usize)>>,
+}
+
+/// Digraph exception string found within a word
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExceptionMatch<'a> {
+    /// Index of exception group
+    pub group: usize,
+    /// Name of exception group, ie. the digraph it applies to
+    pub digraph: &'a str,
+    /// Matched exception string, or prefix rule such as `pod-ž`
+    pub exception: &'a str,
+    /// Index of first character of match within the word
+    pub start: usize,
+    /// Index of character after the match within the word
+    pub end: usize,
+    /// Index of first letter of the only digraph the exception applies
+    /// to, if it marks a split position
+    pub split: Option<usize>,
+}
+
+/// All exception strings found within a word
+pub struct WordExceptions<'a> {
+    matches: Vec<ExceptionMatch<'a>>,
+}
+
+impl ExceptionMatcher {
+    pub fn new(groups: Vec<ExceptionGroup>) -> Result<Self, Error> {
+        let mut texts = Vec::new();
+        let mut patterns = Vec::new();
+        let mut regexes = Vec::new();
+        for (g, group) in groups.iter().enumerate() {
+            for (e, exception) in group.exceptions.iter().enumerate() {
+                let pattern = Pattern::parse(exception)
+                    .map_err(|message| Error::InvalidException(exception.clone(), message))?;
+                match pattern {
+                    Pattern::Plain {
+                        text,
+                        start,
+                        end,
+                        split,
+                    } => {
+                        texts.push(text);
+                        patterns.push(PlainPattern {
+                            group: g,
+                            exception: e,
+                            start,
+                            end,
+                            split,
+                        });
+                    }
+                    Pattern::Regex(re) => regexes.push((g, e, re)),
+                }
+            }
+        }
+        let automaton =
+            AhoCorasick::new(&texts).expect("digraph exceptions should fit into automaton");
+        let rules = groups
+            .iter()
+            .map(|group| {
+                let second = group.name.chars().skip(1).collect::<String>();
+                group
+                    .rule
+                    .prefixes
+                    .iter()
+                    .map(|p| (format!("{}{}", p, second), format!("{}-{}", p, second), p.len()))
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            groups,
+            automaton,
+            patterns,
+            regexes,
+            rules,
+        })
+    }
+
+    /// Lets prefix rules decide words which no exception string covers
+    pub fn prefix_rules(mut self, enabled: bool) -> Self {
+        if !enabled {
+            self.rules.iter_mut().for_each(Vec::clear);
+        }
+        self
+    }
+
+    pub fn groups(&self) -> &[ExceptionGroup] {
+        &self.groups
+    }
+
+    /// Returns index of group which contains provided source character
+    /// and its position within the group
+    pub fn group(&self, character: &[char]) -> Option<(usize, usize)> {
+        self.groups.iter().enumerate().find_map(|(g, group)| {
+            group
+                .source
+                .iter()
+                .position(|l| *l.value == *character)
+                .map(|i| (g, i))
+        })
+    }
+
+    /// Checks if a word is denied by the prefix rule of a group, so its
+    /// digraph is known to be kept
+    pub fn denied(&self, group: usize, word: &str) -> bool {
+        let word = word
+            .trim_start_matches(|c: char| !c.is_alphabetic())
+            .to_lowercase();
+        self.groups[group]
+            .rule
+            .deny
+            .iter()
+            .any(|d| word.starts_with(d.as_str()))
+    }
+
+    /// Finds all exception strings within a word, ignoring case. Prefix
+    /// rules match at the start of the word, after any punctuation.
+    pub fn find(&self, word: &[char]) -> WordExceptions<'_> {
+        let mut lowercase = String::with_capacity(word.len());
+        // Index of original character for each byte of lowercase word
+        let mut origin: Vec<usize> = Vec::with_capacity(word.len() + 1);
+        for (i, letter) in word.iter().enumerate() {
+            for c in letter.to_lowercase() {
+                lowercase.push(c);
+                origin.resize(lowercase.len(), i);
+            }
+        }
+        origin.push(word.len());
+        // Anchors refer to the letters of the word, without punctuation
+        let lead = lowercase
+            .find(char::is_alphabetic)
+            .unwrap_or(lowercase.len());
+        let tail = lowercase
+            .rfind(char::is_alphabetic)
+            .map_or(lead, |i| i + lowercase[i..].chars().next().map_or(0, char::len_utf8));
+        // Index of first letter of the digraph split at a byte offset
+        let split_at = |split: usize| {
+            let before = lowercase[..split].chars().last().map_or(0, char::len_utf8);
+            origin[split - before]
+        };
+        let mut matches = Vec::new();
+        for m in self.automaton.find_overlapping_iter(&lowercase) {
+            let pattern = &self.patterns[m.pattern().as_usize()];
+            if (pattern.start && m.start() != lead) || (pattern.end && m.end() != tail) {
+                continue;
+            }
+            matches.push(ExceptionMatch {
+                group: pattern.group,
+                digraph: &self.groups[pattern.group].name,
+                exception: &self.groups[pattern.group].exceptions[pattern.exception],
+                start: origin[m.start()],
+                end: origin[m.end()],
+                split: pattern.split.map(|split| split_at(m.start() + split)),
+            });
+        }
+        let rest = &lowercase[lead..tail];
+        for (group, exception, re) in &self.regexes {
+            for captures in re.captures_iter(rest) {
+                let m = captures.get(0).expect("regex match should have a span");
+                matches.push(ExceptionMatch {
+                    group: *group,
+                    digraph: &self.groups[*group].name,
+                    exception: &self.groups[*group].exceptions[*exception],
+                    start: origin[lead + m.start()],
+                    end: origin[lead + m.end()],
+                    split: captures
+                        .get(1)
+                        .filter(|c| c.end() > 0)
+                        .map(|c| split_at(lead + c.end())),
+                });
+            }
+        }
+        for (g, group) in self.groups.iter().enumerate() {
+            // Rules only decide words which no exception string covers
+            if matches.iter().any(|m| m.group == g)
+                || group.rule.deny.iter().any(|d| rest.starts_with(d.as_str()))
+            {
+                continue;
+            }
+            let rule = self.rules[g].iter().find(|(pattern, _, _)| {
+                rest.starts_with(pattern.as_str())
+                    && rest[pattern.len()..]
+                        .chars()
+                        .next()
+                        .map_or(false, |c| group.rule.stem.contains(&c))
+            });
+            if let Some((pattern, label, prefix)) = rule {
+                matches.push(ExceptionMatch {
+                    group: g,
+                    digraph: &group.name,
+                    exception: label,
+                    start: origin[lead],
+                    end: origin[lead + pattern.len()],
+                    split: Some(split_at(lead + prefix)),
+                });
+            }
+        }
+        WordExceptions { matches }
+    }
+}
+
+impl<'a> WordExceptions<'a> {
+    /// Returns all exception strings within the word
+    pub fn matches(self) -> Vec<ExceptionMatch<'a>> {
+        self.matches
+    }
+
+    /// Returns the first exception string of a group within the word
+    pub fn group(&self, group: usize) -> Option<&ExceptionMatch<'a>> {
+        self.matches
+            .iter()
+            .filter(|m| m.group == group)
+            .min_by_key(|m| m.start)
+    }
+
+    /// Returns the first exception string of a group which applies to
+    /// the digraph starting at character `position`
+    pub fn at(&self, group: usize, position: usize) -> Option<&ExceptionMatch<'a>> {
+        self.matches
+            .iter()
+            .filter(|m| m.group == group && m.split.map_or(true, |s| s == position))
+            .min_by_key(|m| m.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() -> Result<(), Error> {
+        let matcher = ExceptionMatcher::new(ExceptionGroup::builtin(false))?;
+        let word = "NEKONJUGOVANI".chars().collect::<Vec<_>>();
+        let found = matcher.find(&word);
+        let m = found.group(2).unwrap();
+        assert_eq!((m.digraph, m.exception), ("nj", "nekonjug"));
+        assert_eq!((m.start, m.end), (0, 8));
+        assert!(found.group(0).is_none());
+        assert!(found.group(1).is_none());
+        // Positions refer to characters of original word
+        let word = "ǅƵpodžupan".chars().collect::<Vec<_>>();
+        let found = matcher.find(&word);
+        let m = found.group(1).unwrap();
+        assert_eq!((m.exception, m.start, m.end), ("podžupan", 2, 10));
+        Ok(())
+    }
+
+    #[test]
+    fn test_exception_list() -> Result<(), Error> {
+        let list = ExceptionList::parse(
+            "# Medical terms\n\n[nj]\ninjekcion  # comment\n!tanjug\n[DZ]\n!NADŽIV\npodžbun\n",
+        )?;
+        let mut groups = ExceptionGroup::builtin(false);
+        list.apply(&mut groups)?;
+        assert!(groups[2].exceptions.contains(&"injekcion".to_owned()));
+        assert!(!groups[2].exceptions.contains(&"tanjug".to_owned()));
+        assert!(groups[1].exceptions.contains(&"podžbun".to_owned()));
+        assert!(!groups[1].exceptions.contains(&"nadživ".to_owned()));
+        // Malformed lists
+        assert!(matches!(ExceptionList::parse("injekc"), Err(Error::ExceptionList(1, _))));
+        assert!(matches!(
+            ExceptionList::parse("[nj]\n\nin jekc"),
+            Err(Error::ExceptionList(3, _))
+        ));
+        let list = ExceptionList::parse("[lj]\nljiljan")?;
+        assert!(matches!(list.apply(&mut groups), Err(Error::UnknownExceptionGroup(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_patterns() -> Result<(), Error> {
+        let list = ExceptionList::parse("[nj]\n!injekc\nin|jekc\n^anj$\nsnj$\n/^bi(n)jat/\n")?;
+        let mut groups = ExceptionGroup::builtin(false);
+        list.apply(&mut groups)?;
+        let matcher = ExceptionMatcher::new(groups)?;
+        for (word, group, expected) in [
+            ("DJB", 0, Some(("^djb", 0, 3, None))),
+            ("ADJB", 0, None),
+            ("(Anj)", 2, Some(("^anj$", 1, 4, None))),
+            ("banj", 2, None),
+            ("Usnj,", 2, Some(("snj$", 1, 4, None))),
+            ("usnjak", 2, None),
+            ("injekcija", 2, Some(("in|jekc", 0, 6, Some(1)))),
+            ("Binjatura", 2, Some(("/^bi(n)jat/", 0, 6, Some(2)))),
+            ("abinjat", 2, None),
+        ] {
+            let chars = word.chars().collect::<Vec<_>>();
+            let found = matcher.find(&chars);
+            let found = found
+                .group(group)
+                .map(|m| (m.exception, m.start, m.end, m.split));
+            assert_eq!(found, expected, "{}", word);
+        }
+        // Exception with a split position applies to a single digraph
+        let chars = "injekcijanj".chars().collect::<Vec<_>>();
+        let found = matcher.find(&chars);
+        assert!(found.at(2, 1).is_some());
+        assert!(found.at(2, 9).is_none());
+        // Malformed patterns
+        for (text, line) in [
+            ("[nj]\n^$", 2),
+            ("[nj]\nin|je|kc", 2),
+            ("[nj]\nabc\n!|nj", 3),
+            ("[nj]\n/in(j/", 2),
+        ] {
+            assert!(
+                matches!(ExceptionList::parse(text), Err(Error::ExceptionList(l, _)) if l == line),
+                "{}",
+                text
+            );
+        }
+        let mut list = ExceptionList::new();
+        list.add("nj", "/in(j/");
+        let mut groups = ExceptionGroup::builtin(false);
+        list.apply(&mut groups)?;
+        assert!(matches!(ExceptionMatcher::new(groups), Err(Error::InvalidException(_, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_rule() -> Result<(), Error> {
+        let matcher = ExceptionMatcher::new(ExceptionGroup::builtin(false))?;
+        for (word, group, expected) in [
+            ("podžbunje", 1, Some(("pod-ž", 0, 4))),
+            ("(Predžetveni", 1, Some(("pred-ž", 1, 6))),
+            ("ODJURIO", 0, Some(("odjur", 0, 5))),
+            ("nadjunačiti", 0, Some(("nadjunač", 0, 8))),
+            ("odjuhnuti", 0, Some(("od-j", 0, 3))),
+            ("odžačar", 1, None),
+            ("Nadžak", 1, None),
+            ("podžtekst", 1, None),
+            ("rodžak", 1, None),
+        ] {
+            let chars = word.chars().collect::<Vec<_>>();
+            let found = matcher.find(&chars);
+            let found = found.group(group).map(|m| (m.exception, m.start, m.end));
+            assert_eq!(found, expected, "{}", word);
+        }
+        // Rules only split the digraph after the prefix
+        let chars = "podžadžak".chars().collect::<Vec<_>>();
+        let found = matcher.find(&chars);
+        assert_eq!(found.group(1).unwrap().split, Some(2));
+        assert!(found.at(1, 2).is_some());
+        assert!(found.at(1, 5).is_none());
+        let matcher = ExceptionMatcher::new(ExceptionGroup::builtin(false))?.prefix_rules(false);
+        assert!(matcher.find(&chars).group(1).is_none());
+        // Suppressed words are denied by prefix rules
+        let mut groups = ExceptionGroup::builtin(false);
+        ExceptionList::parse("[dž]\n!podžbun\n")?.apply(&mut groups)?;
+        let matcher = ExceptionMatcher::new(groups)?;
+        let chars = "podžbunje".chars().collect::<Vec<_>>();
+        assert!(matcher.find(&chars).group(1).is_none());
+        assert!(matcher.denied(1, "Podžbunje"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_group() -> Result<(), Error> {
+        let matcher = ExceptionMatcher::new(ExceptionGroup::builtin(false))?;
+        assert_eq!(matcher.group(&['D', 'j']), Some((0, 3)));
+        assert_eq!(matcher.group(&['ǆ']), Some((1, 7)));
+        assert_eq!(matcher.group(&['n', 'j']), Some((2, 4)));
+        assert_eq!(matcher.group(&['l', 'j']), None);
+        Ok(())
+    }
+}
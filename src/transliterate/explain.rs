@@ -0,0 +1,101 @@
+use std::fmt;
+use std::ops::Range;
+
+use serde::Serialize;
+
+use super::align::Span;
+
+/// Reason why a word was transliterated or kept as is
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum WordAction {
+    /// Word has been transliterated
+    Converted,
+    /// Word has been transliterated, keeping digraphs of the exception
+    /// strings it contains as separate letters
+    ExceptionApplied { exceptions: Vec<String> },
+    /// Word contains a letter not found in source charset
+    ForeignSkipped { letter: char },
+    /// Word contains a URL, an email address or a unit
+    LinkSkipped,
+    /// Word is one of the protected terms
+    ProtectedSkipped,
+    /// Root of the word is kept as is, while the case suffix following
+    /// it has been transliterated
+    SuffixConverted { root: String },
+}
+
+/// Transliteration of a single word
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WordReport {
+    /// Byte index of the word within the text
+    pub start: usize,
+    /// Byte index after the word within the text
+    pub end: usize,
+    pub input: String,
+    /// Word after its look-alike letters of the other script have been
+    /// repaired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repaired: Option<String>,
+    #[serde(flatten)]
+    pub action: WordAction,
+    pub output: String,
+    /// Spans of each transliterated unit, relative to the word and its
+    /// output
+    #[serde(skip)]
+    pub units: Vec<Span>,
+}
+
+impl WordReport {
+    pub(super) fn new(start: usize, input: &str) -> Self {
+        Self {
+            start,
+            end: start + input.len(),
+            input: input.to_owned(),
+            repaired: None,
+            action: WordAction::Converted,
+            output: String::new(),
+            units: Vec::new(),
+        }
+    }
+
+    pub(super) fn unit(&mut self, input: Range<usize>, output: Range<usize>) {
+        self.units.push(Span { input, output });
+    }
+
+    pub(super) fn exception(&mut self, exception: &str) {
+        match &mut self.action {
+            WordAction::ExceptionApplied { exceptions } => exceptions.push(exception.to_owned()),
+            action => {
+                *action = WordAction::ExceptionApplied {
+                    exceptions: vec![exception.to_owned()],
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for WordAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Converted => write!(f, "converted"),
+            Self::ExceptionApplied { exceptions } => {
+                write!(f, "exception applied ({})", exceptions.join(", "))
+            }
+            Self::ForeignSkipped { letter } => write!(f, "foreign skipped ({})", letter),
+            Self::LinkSkipped => write!(f, "link skipped"),
+            Self::ProtectedSkipped => write!(f, "protected skipped"),
+            Self::SuffixConverted { root } => write!(f, "suffix converted ({})", root),
+        }
+    }
+}
+
+impl fmt::Display for WordReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}\t{}\t{}", self.start, self.end, self.input, self.action)?;
+        if let Some(repaired) = &self.repaired {
+            write!(f, ", repaired as {}", repaired)?;
+        }
+        write!(f, "\t{}", self.output)
+    }
+}
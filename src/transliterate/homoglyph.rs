@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+
+use super::charmaps;
+use super::detect::letter_charset;
+use super::Charset;
+
+/// Returns letter of the other script which looks the same
+pub(super) fn homoglyph(c: char, into_cyrillic: bool) -> Option<char> {
+    charmaps::HOMOGLYPHS
+        .iter()
+        .find_map(|&(latin, cyrillic)| match into_cyrillic {
+            true if c == latin => Some(cyrillic),
+            false if c == cyrillic => Some(latin),
+            _ => None,
+        })
+}
+
+/// Checks if a letter is Cyrillic, returning `None` for non-letters
+pub(super) fn is_cyrillic(c: char) -> Option<bool> {
+    match letter_charset(c) {
+        Some(Charset::Cyrillic) => Some(true),
+        Some(_) => Some(false),
+        None if homoglyph(c, true).is_some() => Some(false),
+        None if homoglyph(c, false).is_some() => Some(true),
+        None => None,
+    }
+}
+
+/// Returns counts of Latin and Cyrillic letters in a word, each as a
+/// pair of counts of all letters and of letters without a look-alike
+pub(super) fn letter_counts(word: &str) -> ((usize, usize), (usize, usize)) {
+    let (mut latin, mut cyrillic) = ((0, 0), (0, 0));
+    for c in word.chars() {
+        let distinct = homoglyph(c, true).or_else(|| homoglyph(c, false)).is_none() as usize;
+        match is_cyrillic(c) {
+            Some(true) => cyrillic = (cyrillic.0 + 1, cyrillic.1 + distinct),
+            Some(false) => latin = (latin.0 + 1, latin.1 + distinct),
+            None => {}
+        }
+    }
+    (latin, cyrillic)
+}
+
+/// Replaces letters of a word which look like letters of its dominant
+/// script. The script is decided by letters without a look-alike, or
+/// by the majority of letters if all of them have one. Returns `None`
+/// if the word is written in a single script or can't be repaired.
+pub fn repair(word: &str) -> Option<String> {
+    let (latin, cyrillic) = letter_counts(word);
+    if latin.0 == 0 || cyrillic.0 == 0 {
+        return None;
+    }
+    let into_cyrillic = match (latin.1, cyrillic.1) {
+        (0, 0) => match latin.0.cmp(&cyrillic.0) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => return None,
+        },
+        (0, _) => true,
+        (_, 0) => false,
+        _ => return None,
+    };
+    word.chars()
+        .map(|c| match is_cyrillic(c) {
+            Some(cyrillic) if cyrillic != into_cyrillic => homoglyph(c, into_cyrillic),
+            _ => Some(c),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair() {
+        for (word, expected) in [
+            // Latin letters in Cyrillic words
+            ("Jaвop", Some("Јавор")),
+            ("Јaвор,", Some("Јавор,")),
+            ("хaос", Some("хаос")),
+            ("Сoca", Some("Coca")),
+            // Cyrillic letters in Latin words
+            ("Ljubičicа", Some("Ljubičica")),
+            ("PОBEDA", Some("POBEDA")),
+            ("Jaо", Some("Jao")),
+            // Words in a single script or without a dominant one
+            ("Javor", None),
+            ("Јавор", None),
+            ("123", None),
+            ("Jо", None),
+            ("šљива", None),
+            ("Ljubiчica", None),
+        ] {
+            assert_eq!(repair(word).as_deref(), expected, "{}", word);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+
+use super::charmaps::{self, Case};
+
+/// Digraph exception derived from a Cyrillic corpus
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinedException {
+    /// Name of exception group, ie. the digraph it applies to
+    pub digraph: String,
+    /// Shortest part of the words which no word with a true digraph
+    /// contains
+    pub exception: String,
+    /// Number of words in the corpus covered by the exception
+    pub count: usize,
+}
+
+// Exception group and position of letters written as a Latin digraph
+type Split = (usize, usize);
+
+/// Returns index of exception group of Cyrillic letters which are
+/// written as a Latin digraph
+fn split_group(first: char, second: char) -> Option<usize> {
+    let name = match (first, second) {
+        ('д', 'ј') => "dj",
+        ('д', 'ж') => "dž",
+        ('н', 'ј') => "nj",
+        _ => return None,
+    };
+    charmaps::DIGRAPH_EXCEPTIONS
+        .iter()
+        .position(|e| e.name == name)
+}
+
+/// Converts a lowercase Cyrillic word into Latin, writing "ђ" as "dj".
+/// Returns the word as characters together with the group and the
+/// position of each pair of letters written as a Latin digraph, or
+/// `None` if the word contains other letters.
+fn latinize(word: &str) -> Option<(Vec<char>, Vec<Split>)> {
+    let chars = word.chars().collect::<Vec<char>>();
+    let mut latin = Vec::with_capacity(chars.len() + 2);
+    let mut splits = Vec::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if let Some(&next) = chars.get(i + 1) {
+            if let Some(group) = split_group(c, next) {
+                splits.push((group, latin.len()));
+            }
+        }
+        match c {
+            'ђ' => latin.extend(['d', 'j'].iter()),
+            _ => {
+                let i = charmaps::CYRILLIC_CLEAN
+                    .iter()
+                    .position(|l| l.case == Case::Lower && *l.value == [c])?;
+                latin.extend(charmaps::LATIN_CLEAN[i].value.iter());
+            }
+        }
+    }
+    Some((latin, splits))
+}
+
+/// Finds Latin digraph exceptions in a correctly written Cyrillic
+/// corpus. Each word where "дј", "дж" or "нј" are separate letters is
+/// reduced to its shortest part around them, at least one letter longer,
+/// which doesn't occur in any word of the corpus written with "ђ", "џ"
+/// or "њ". Exceptions which contain a shorter one are merged into it.
+///
+/// Exceptions are sorted by group, and then by count of words they
+/// cover, in descending order.
+///
+/// # Example
+///
+/// ```
+/// use translitrs::mine_exceptions;
+///
+/// let mined = mine_exceptions("Одјек се чуо, ђак је отишао у подјелу.");
+/// assert_eq!(mined.len(), 1);
+/// assert_eq!((mined[0].digraph.as_str(), mined[0].exception.as_str()), ("dj", "odj"));
+/// assert_eq!(mined[0].count, 2);
+/// ```
+pub fn mine_exceptions(corpus: &str) -> Vec<MinedException> {
+    let groups = charmaps::DIGRAPH_EXCEPTIONS;
+    // Count and splits of each word
+    let mut split_words: HashMap<Vec<char>, (usize, Vec<Split>)> = HashMap::new();
+    // Parts of Latin words with a true digraph which contain it, by group
+    let mut digraph_parts: Vec<HashSet<String>> = vec![HashSet::new(); groups.len()];
+    for word in corpus.split(|c: char| !c.is_alphabetic()) {
+        let (latin, splits) = match latinize(&word.to_lowercase()) {
+            Some(latinized) => latinized,
+            None => continue,
+        };
+        if !splits.is_empty() {
+            split_words.entry(latin).or_insert((0, splits)).0 += 1;
+            continue;
+        }
+        for (group, parts) in groups.iter().zip(digraph_parts.iter_mut()) {
+            let digraph = group.name.chars().collect::<Vec<char>>();
+            for position in 0..latin.len().saturating_sub(1) {
+                if latin[position..position + 2] != digraph[..] {
+                    continue;
+                }
+                // Only parts around the digraph are compared with exceptions
+                for start in 0..=position {
+                    for end in position + 2..=latin.len() {
+                        if end - start >= 3 {
+                            parts.insert(latin[start..end].iter().collect());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let mut counts: HashMap<(usize, String), usize> = HashMap::new();
+    for (word, (count, splits)) in &split_words {
+        for &(group, position) in splits {
+            let distinct = |s: &str| !digraph_parts[group].contains(s);
+            // Bare digraph would disable it, so exceptions include a letter more
+            let shortest = (3..=word.len()).find_map(|len| {
+                ((position + 2).saturating_sub(len)..=position)
+                    .filter(|start| start + len <= word.len())
+                    .map(|start| word[start..start + len].iter().collect::<String>())
+                    .find(|s| distinct(s))
+            });
+            // Words also written with a true digraph can't be decided
+            if let Some(exception) = shortest {
+                *counts.entry((group, exception)).or_insert(0) += count;
+            }
+        }
+    }
+    let mut mined = counts.into_iter().collect::<Vec<_>>();
+    // Shorter exceptions go first, so longer ones are merged into them
+    mined.sort_by(|((g1, e1), _), ((g2, e2), _)| (g1, e1.len(), e1).cmp(&(g2, e2.len(), e2)));
+    let mut merged: Vec<((usize, String), usize)> = Vec::with_capacity(mined.len());
+    // Index of each merged exception
+    let mut index: HashMap<(usize, String), usize> = HashMap::new();
+    for ((group, exception), count) in mined {
+        let chars = exception.chars().collect::<Vec<char>>();
+        // Shortest merged exception contained in this one
+        let shorter = (3..=chars.len()).find_map(|len| {
+            (0..=chars.len() - len)
+                .filter_map(|start| {
+                    let part = chars[start..start + len].iter().collect::<String>();
+                    index.get(&(group, part)).copied()
+                })
+                .min()
+        });
+        match shorter {
+            Some(i) => merged[i].1 += count,
+            None => {
+                index.insert((group, exception.clone()), merged.len());
+                merged.push(((group, exception), count));
+            }
+        }
+    }
+    merged.sort_by(|((g1, e1), c1), ((g2, e2), c2)| (g1, c2, e1).cmp(&(g2, c1, e2)));
+    merged
+        .into_iter()
+        .map(|((group, exception), count)| MinedException {
+            digraph: groups[group].name.to_owned(),
+            exception,
+            count,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mine_exceptions() {
+        let corpus = "Одјек одјека. Ђак, џак и Инјекција инјекције коњу. \
+                      Наџивети надживети, поджупан и PODŽUPAN.";
+        let mined = mine_exceptions(corpus)
+            .into_iter()
+            .map(|m| (m.digraph, m.exception, m.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mined,
+            vec![
+                ("dj".to_owned(), "odj".to_owned(), 2),
+                ("dž".to_owned(), "odž".to_owned(), 1),
+                ("nj".to_owned(), "inj".to_owned(), 2),
+            ]
+        );
+        assert!(mine_exceptions("ђак џак њива").is_empty());
+    }
+}
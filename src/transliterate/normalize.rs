@@ -0,0 +1,170 @@
+use std::{cmp, str};
+
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::{is_nfc_quick, is_nfd_quick, IsNormalized, UnicodeNormalization};
+
+use super::align::Span;
+use super::Error;
+
+/// Unicode normal form of transliterated text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    /// Characters are written as they are found in the input and
+    /// charmaps
+    Keep,
+    /// Canonical composition, such as "č"
+    Nfc,
+    /// Canonical decomposition, such as "c" followed by a caron
+    Nfd,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self::Keep
+    }
+}
+
+impl str::FromStr for Normalization {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "keep" => Ok(Self::Keep),
+            "nfc" => Ok(Self::Nfc),
+            "nfd" => Ok(Self::Nfd),
+            _ => Err(Error::UnknownNormalization(s.to_owned())),
+        }
+    }
+}
+
+/// Byte indices of run boundaries in a text and in its normalized form
+pub(super) struct Runs {
+    from: Vec<usize>,
+    into: Vec<usize>,
+}
+
+impl Runs {
+    /// Maps normalized text indices onto the original text
+    pub(super) fn reversed(self) -> Self {
+        Self {
+            from: self.into,
+            into: self.from,
+        }
+    }
+
+    /// Maps start of a range, moving it to the start of its run
+    fn start(&self, i: usize) -> usize {
+        self.into[self.from.partition_point(|&b| b <= i) - 1]
+    }
+
+    /// Maps end of a range, moving it to the end of its run
+    fn end(&self, i: usize) -> usize {
+        self.into[self.from.partition_point(|&b| b < i)]
+    }
+
+    /// Maps input or output ranges of units, merging units which end up
+    /// within the same run
+    pub(super) fn remap(&self, units: Vec<Span>, input: bool) -> Vec<Span> {
+        let mut remapped: Vec<Span> = Vec::with_capacity(units.len());
+        for mut unit in units {
+            let range = match input {
+                true => &mut unit.input,
+                false => &mut unit.output,
+            };
+            *range = self.start(range.start)..self.end(range.end);
+            match remapped.last_mut() {
+                Some(last)
+                    if unit.input.start < last.input.end || unit.output.start < last.output.end =>
+                {
+                    last.input.end = cmp::max(last.input.end, unit.input.end);
+                    last.output.end = cmp::max(last.output.end, unit.output.end);
+                }
+                _ => remapped.push(unit),
+            }
+        }
+        remapped
+    }
+}
+
+/// Normalizes each run of a character followed by combining marks,
+/// keeping only normalized runs which are accepted. Returns `None` if
+/// the text is left unchanged.
+pub(super) fn normalize(
+    text: &str,
+    form: Normalization,
+    accept: impl Fn(&str) -> bool,
+) -> Option<(String, Runs)> {
+    let normalized = match form {
+        Normalization::Keep => true,
+        Normalization::Nfc => is_nfc_quick(text.chars()) == IsNormalized::Yes,
+        Normalization::Nfd => is_nfd_quick(text.chars()) == IsNormalized::Yes,
+    };
+    if normalized {
+        return None;
+    }
+    let mut output = String::with_capacity(text.len());
+    let mut runs = Runs {
+        from: vec![0],
+        into: vec![0],
+    };
+    let mut starts = text
+        .char_indices()
+        .filter(|&(i, c)| i == 0 || canonical_combining_class(c) == 0)
+        .map(|(i, _)| i)
+        .chain(Some(text.len()))
+        .peekable();
+    while let Some(start) = starts.next() {
+        let end = match starts.peek() {
+            Some(&end) => end,
+            None => break,
+        };
+        let run = &text[start..end];
+        let normal: String = match form {
+            Normalization::Nfd => run.nfd().collect(),
+            _ => run.nfc().collect(),
+        };
+        match normal != run && accept(&normal) {
+            true => output.push_str(&normal),
+            false => output.push_str(run),
+        }
+        runs.from.push(end);
+        runs.into.push(output.len());
+    }
+    match output == text {
+        true => None,
+        false => Some((output, runs)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let (composed, runs) =
+            normalize("c\u{30C}a\u{301}", Normalization::Nfc, |run| run.starts_with('č')).unwrap();
+        assert_eq!(composed, "ča\u{301}");
+        let units = vec![
+            Span {
+                input: 0..2,
+                output: 0..2,
+            },
+            Span {
+                input: 2..3,
+                output: 2..3,
+            },
+            Span {
+                input: 3..5,
+                output: 3..5,
+            },
+        ];
+        let remapped = runs.reversed().remap(units, true);
+        assert_eq!(remapped[0].input, 0..3);
+        assert_eq!(remapped[1].input, 3..6);
+        assert_eq!(remapped[1].output, 2..5);
+        assert!(normalize("čaj", Normalization::Nfc, |_| true).is_none());
+        let (decomposed, _) = normalize("čaj", Normalization::Nfd, |_| true).unwrap();
+        assert_eq!(decomposed, "c\u{30C}aj");
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::charmaps;
+use super::Error;
+
+/// Words which are kept untouched, such as brand names and acronyms
+///
+/// Lists are plain text files with one term per line, where `#` starts
+/// a comment.
+///
+/// ```text
+/// # Brands
+/// iPhone
+/// USB
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use translitrs::{ProtectedTerms, Transliterator};
+///
+/// let t = Transliterator::builder()
+///     .protected_terms(ProtectedTerms::builtin())
+///     .protected_terms(ProtectedTerms::parse("Jabuka\n").unwrap())
+///     .build()
+///     .unwrap();
+/// assert_eq!(t.process("Jabuka i USB").unwrap(), "Jabuka и USB");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ProtectedTerms {
+    terms: Vec<String>,
+}
+
+impl ProtectedTerms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns built-in list of common brand names and acronyms
+    pub fn builtin() -> Self {
+        Self {
+            terms: charmaps::PROTECTED_TERMS
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+        }
+    }
+
+    /// Parses protected terms from text
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut list = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.contains(char::is_whitespace) {
+                return Err(Error::ProtectedTerms(i + 1, "term contains spaces".to_owned()));
+            }
+            list.add(line);
+        }
+        Ok(list)
+    }
+
+    /// Loads protected terms from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Adds a term to the list
+    pub fn add(&mut self, term: &str) {
+        self.terms.push(term.to_owned());
+    }
+}
+
+/// Protected terms of all lists, looked up by whole words
+pub struct TermMatcher {
+    terms: HashSet<String>,
+    ignore_case: bool,
+    suffixes: bool,
+}
+
+impl TermMatcher {
+    pub fn new(lists: &[ProtectedTerms], ignore_case: bool, suffixes: bool) -> Self {
+        let terms = lists
+            .iter()
+            .flat_map(|l| l.terms.iter())
+            .map(|t| match ignore_case {
+                true => t.to_lowercase(),
+                false => t.clone(),
+            })
+            .collect();
+        Self {
+            terms,
+            ignore_case,
+            suffixes,
+        }
+    }
+
+    fn contains(&self, term: &str) -> bool {
+        match self.ignore_case {
+            true => self.terms.contains(&term.to_lowercase()),
+            false => self.terms.contains(term),
+        }
+    }
+
+    /// Checks if a word, stripped of surrounding punctuation, is one of
+    /// the terms. Terms followed by a hyphen or an apostrophe and a
+    /// case suffix (eg. Google-u) match if suffixes are tolerated.
+    pub fn matches(&self, word: &str) -> bool {
+        if self.terms.is_empty() {
+            return false;
+        }
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+        if word.is_empty() {
+            return false;
+        }
+        if self.contains(word) {
+            return true;
+        }
+        if !self.suffixes {
+            return false;
+        }
+        match word
+            .char_indices()
+            .rev()
+            .find(|(_, c)| matches!(c, '-' | '\'' | '’'))
+        {
+            Some((i, separator)) => {
+                let suffix = word[i + separator.len_utf8()..].to_lowercase();
+                charmaps::CASE_SUFFIXES.contains(&suffix.as_str()) && self.contains(&word[..i])
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() -> Result<(), Error> {
+        let lists = [
+            ProtectedTerms::builtin(),
+            ProtectedTerms::parse("# Comment\n\nMaxi  # store\n")?,
+        ];
+        let matcher = TermMatcher::new(&lists, false, false);
+        for word in [
+            "Google", "Adobe", "Nokia", "USB", "NATO", "iPhone", "(IBM),", "Maxi",
+        ] {
+            assert!(matcher.matches(word), "{}", word);
+        }
+        for word in ["iphone", "USB-u", "USBov", "Max", "", "..."] {
+            assert!(!matcher.matches(word), "{}", word);
+        }
+        let matcher = TermMatcher::new(&lists, true, true);
+        for word in [
+            "iphone",
+            "Google-u",
+            "NATO-u",
+            "iPhone’om",
+            "IBM-ovim.",
+            "maxi-ja",
+        ] {
+            assert!(matcher.matches(word), "{}", word);
+        }
+        for word in ["USB-xyz", "-u", "IBM-u-u"] {
+            assert!(!matcher.matches(word), "{}", word);
+        }
+        assert!(matches!(
+            ProtectedTerms::parse("Maxi\nNew York"),
+            Err(Error::ProtectedTerms(2, _))
+        ));
+        Ok(())
+    }
+}
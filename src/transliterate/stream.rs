@@ -0,0 +1,355 @@
+use std::io::{self, Read, Write};
+use std::str;
+
+use super::Transliterator;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Returns length of the longest prefix of `pending` which can be
+/// transliterated without splitting a word or a UTF-8 sequence. Bytes
+/// before `scanned` are known to be valid UTF-8 without whitespace, so
+/// only the rest is checked, and `scanned` is moved past it.
+fn complete_length(pending: &[u8], scanned: &mut usize, last: bool) -> io::Result<usize> {
+    let rest = &pending[*scanned..];
+    let text = match str::from_utf8(rest) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() && !last => {
+            str::from_utf8(&rest[..e.valid_up_to()]).expect("prefix is valid UTF-8")
+        }
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    };
+    let start = *scanned;
+    *scanned += text.len();
+    if last {
+        return Ok(pending.len());
+    }
+    // Words are delimited by whitespace, so everything up to the last
+    // whitespace character is processed exactly as a whole text would be
+    Ok(match text.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+        Some((i, c)) => start + i + c.len_utf8(),
+        None => 0,
+    })
+}
+
+/// Transliterates text from the front of `pending` up to the word
+/// boundary, appending it to `output`
+fn transliterate(
+    transliterator: &Transliterator,
+    pending: &mut Vec<u8>,
+    scanned: &mut usize,
+    output: &mut String,
+    last: bool,
+) -> io::Result<()> {
+    let length = complete_length(pending, scanned, last)?;
+    if length == 0 {
+        return Ok(());
+    }
+    let text = str::from_utf8(&pending[..length]).expect("complete text is valid UTF-8");
+    transliterator
+        .process_into(text, output)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    pending.drain(..length);
+    *scanned -= length;
+    Ok(())
+}
+
+/// Writer adapter which transliterates text before passing it to the
+/// underlying writer.
+///
+/// Only the last incomplete word is kept in memory, so arbitrarily large
+/// inputs are processed in constant memory. Remaining text is written
+/// out by [`TransliteratingWriter::finish`], or when the writer is
+/// dropped.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Write;
+/// use translitrs::{Charset, TransliteratingWriter, Transliterator};
+///
+/// let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+/// let mut writer = TransliteratingWriter::new(Vec::new(), &t);
+/// writer.write_all("Ljubičica i ".as_bytes()).unwrap();
+/// writer.write_all("džem".as_bytes()).unwrap();
+/// assert_eq!(writer.finish().unwrap(), "Љубичица и џем".as_bytes());
+/// ```
+pub struct TransliteratingWriter<'a, W: Write> {
+    inner: Option<W>,
+    transliterator: &'a Transliterator,
+    pending: Vec<u8>,
+    /// Length of the start of `pending` already checked for whitespace
+    scanned: usize,
+    output: String,
+}
+
+impl<'a, W: Write> TransliteratingWriter<'a, W> {
+    pub fn new(inner: W, transliterator: &'a Transliterator) -> Self {
+        Self {
+            inner: Some(inner),
+            transliterator,
+            pending: Vec::with_capacity(CHUNK_SIZE),
+            scanned: 0,
+            output: String::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn write_pending(&mut self, last: bool) -> io::Result<()> {
+        transliterate(
+            self.transliterator,
+            &mut self.pending,
+            &mut self.scanned,
+            &mut self.output,
+            last,
+        )?;
+        if let Some(inner) = self.inner.as_mut() {
+            inner.write_all(self.output.as_bytes())?;
+        }
+        self.output.clear();
+        Ok(())
+    }
+
+    /// Transliterates remaining text and returns the underlying writer.
+    /// Fails if the written text does not end with valid UTF-8.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_pending(true)?;
+        let mut inner = self.inner.take().expect("writer is present until finished");
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<'a, W: Write> Write for TransliteratingWriter<'a, W> {
+    /// Buffers text, writing out complete words once enough of them is
+    /// pending. Text is only accepted after the pending words have been
+    /// written, so a failed write can be retried.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pending.len() >= CHUNK_SIZE {
+            self.write_pending(false)?;
+        }
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Writes out all complete words; the last word is kept until more
+    /// text arrives or the writer is finished
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending(false)?;
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a, W: Write> Drop for TransliteratingWriter<'a, W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_pending(true);
+        }
+    }
+}
+
+/// Reader adapter which transliterates text read from the underlying
+/// reader.
+///
+/// Input is read in chunks and only the last incomplete word is kept
+/// in memory between them.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Read;
+/// use translitrs::{Charset, TransliteratingReader, Transliterator};
+///
+/// let t = Transliterator::new(Charset::Cyrillic, Charset::Latin, false, false, false);
+/// let mut reader = TransliteratingReader::new("Љубичица и џем".as_bytes(), &t);
+/// let mut output = String::new();
+/// reader.read_to_string(&mut output).unwrap();
+/// assert_eq!(output, "Ljubičica i džem");
+/// ```
+pub struct TransliteratingReader<'a, R: Read> {
+    inner: R,
+    transliterator: &'a Transliterator,
+    pending: Vec<u8>,
+    /// Length of the start of `pending` already checked for whitespace
+    scanned: usize,
+    output: String,
+    position: usize,
+    finished: bool,
+}
+
+impl<'a, R: Read> TransliteratingReader<'a, R> {
+    pub fn new(inner: R, transliterator: &'a Transliterator) -> Self {
+        Self {
+            inner,
+            transliterator,
+            pending: Vec::with_capacity(CHUNK_SIZE),
+            scanned: 0,
+            output: String::with_capacity(CHUNK_SIZE),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_output(&mut self) -> io::Result<()> {
+        self.output.clear();
+        self.position = 0;
+        let mut chunk = [0; CHUNK_SIZE];
+        while self.output.is_empty() && !self.finished {
+            let length = match self.inner.read(&mut chunk) {
+                Ok(length) => length,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.finished = length == 0;
+            self.pending.extend_from_slice(&chunk[..length]);
+            transliterate(
+                self.transliterator,
+                &mut self.pending,
+                &mut self.scanned,
+                &mut self.output,
+                self.finished,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: Read> Read for TransliteratingReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.output.len() {
+            self.fill_output()?;
+        }
+        let length = buf.len().min(self.output.len() - self.position);
+        buf[..length]
+            .copy_from_slice(&self.output.as_bytes()[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate::tests::EXAMPLES;
+    use crate::transliterate::Charset;
+
+    /// Reader which returns at most one byte per call
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_writer() -> io::Result<()> {
+        let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        for (lat, cyr, _) in EXAMPLES {
+            // Byte by byte, splitting both words and UTF-8 sequences
+            let mut writer = TransliteratingWriter::new(Vec::new(), &t);
+            for b in lat.as_bytes() {
+                writer.write_all(&[*b])?;
+                writer.flush()?;
+            }
+            assert_eq!(String::from_utf8(writer.finish()?).unwrap(), *cyr);
+        }
+        Ok(())
+    }
+
+    /// Writer which fails its first write without writing anything
+    struct Failing(Vec<u8>, bool);
+
+    impl Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if !self.1 {
+                self.1 = true;
+                return Err(io::Error::new(io::ErrorKind::Other, "failed"));
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writer_retry() -> io::Result<()> {
+        let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        let mut writer = TransliteratingWriter::new(Failing(Vec::new(), false), &t);
+        let text = "njiva ".repeat(CHUNK_SIZE / 6 + 1);
+        writer.write_all(text.as_bytes())?;
+        // Failed write is retried without duplicating text
+        assert!(writer.write(b"kraj").is_err());
+        writer.write_all(b"kraj")?;
+        let output = String::from_utf8(writer.finish()?.0).unwrap();
+        assert_eq!(output, t.process(text + "kraj").unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer_long_word() -> io::Result<()> {
+        let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        let text = format!("{} ljubav", "đnj".repeat(CHUNK_SIZE));
+        let mut writer = TransliteratingWriter::new(Vec::new(), &t);
+        for b in text.as_bytes() {
+            writer.write_all(&[*b])?;
+        }
+        let output = String::from_utf8(writer.finish()?).unwrap();
+        assert_eq!(output, t.process(&text).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer_drop() {
+        let t = Transliterator::new(Charset::Cyrillic, Charset::Latin, false, false, false);
+        let mut output = Vec::new();
+        {
+            let mut writer = TransliteratingWriter::new(&mut output, &t);
+            write!(writer, "Њива џем").unwrap();
+        }
+        assert_eq!(output, "Njiva džem".as_bytes());
+    }
+
+    #[test]
+    fn test_writer_invalid_utf8() {
+        let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        let mut writer = TransliteratingWriter::new(Vec::new(), &t);
+        writer.write_all(&[b'a', 0xC5]).unwrap();
+        assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut writer = TransliteratingWriter::new(Vec::new(), &t);
+        writer.write_all(&[b'a', 0xFF, b' ']).unwrap();
+        assert_eq!(writer.flush().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_reader() -> io::Result<()> {
+        let t = Transliterator::new(Charset::Latin, Charset::Cyrillic, false, false, false);
+        for (lat, cyr, _) in EXAMPLES {
+            let mut output = String::new();
+            TransliteratingReader::new(Trickle(lat.as_bytes()), &t).read_to_string(&mut output)?;
+            assert_eq!(&output, cyr);
+        }
+        let text = EXAMPLES
+            .iter()
+            .map(|(lat, _, _)| *lat)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut output = String::new();
+        TransliteratingReader::new(text.repeat(20).as_bytes(), &t).read_to_string(&mut output)?;
+        assert_eq!(output, t.process(text.repeat(20)).unwrap());
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use super::charmaps::Character;
+
+/// Prefix tree over character sequences of a charmap
+///
+/// Each node stores indices of charmap entries which end in it, so all
+/// entries matching at some position are found in a single walk
+/// instead of comparing against every entry.
+pub struct CharmapTrie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    // Sorted by character
+    children: Vec<(char, usize)>,
+    entries: Vec<usize>,
+}
+
+impl Node {
+    fn child(&self, c: char) -> Option<usize> {
+        self.children
+            .binary_search_by(|(k, _)| k.cmp(&c))
+            .ok()
+            .map(|i| self.children[i].1)
+    }
+}
+
+impl CharmapTrie {
+    pub fn new(charmap: &[Character]) -> Self {
+        let mut nodes = vec![Node::default()];
+        for (index, Character { value, .. }) in charmap.iter().enumerate() {
+            let mut current = 0;
+            for &c in value.iter() {
+                current = match nodes[current].children.binary_search_by(|(k, _)| k.cmp(&c)) {
+                    Ok(i) => nodes[current].children[i].1,
+                    Err(i) => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[current].children.insert(i, (c, next));
+                        next
+                    }
+                };
+            }
+            nodes[current].entries.push(index);
+        }
+        Self { nodes }
+    }
+
+    /// Collects indices of all charmap entries which are a prefix of
+    /// `input` into `matches`, ordered by descending index (ie. the
+    /// order in which entries take priority)
+    pub fn find(&self, input: &[char], matches: &mut Vec<usize>) {
+        matches.clear();
+        let mut current = 0;
+        for &c in input {
+            match self.nodes[current].child(c) {
+                Some(next) => current = next,
+                None => break,
+            }
+            matches.extend(&self.nodes[current].entries);
+        }
+        matches.sort_unstable_by(|a, b| b.cmp(a));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::transliterate::charmaps::{self, Case};
+
+    #[test]
+    fn test_find() {
+        let trie = CharmapTrie::new(charmaps::LATIN_DIRTY);
+        let mut matches = Vec::new();
+        for (input, expected) in [
+            ("", vec![]),
+            ("x", vec![]),
+            ("dž", vec![90, 88, 53]),
+            ("Dja", vec![8, 4]),
+            ("DŽ", vec![42, 40, 4]),
+            ("ǆ", vec![89]),
+        ] {
+            trie.find(&input.chars().collect::<Vec<_>>(), &mut matches);
+            assert_eq!(matches, expected, "{}", input);
+            // Same entries are found by comparing against every one
+            let input = input.chars().collect::<Vec<_>>();
+            let linear = (0..charmaps::LATIN_DIRTY.len())
+                .rev()
+                .filter(|i| input.starts_with(&charmaps::LATIN_DIRTY[*i].value))
+                .collect::<Vec<_>>();
+            assert_eq!(matches, linear);
+        }
+    }
+
+    #[test]
+    fn test_priority() {
+        // Later entries take priority regardless of their length
+        let charmap = &[
+            Character {
+                value: Cow::Borrowed(&['a', 'b']),
+                case: Case::Lower,
+            },
+            Character {
+                value: Cow::Borrowed(&['a']),
+                case: Case::Lower,
+            },
+        ];
+        let mut matches = Vec::new();
+        CharmapTrie::new(charmap).find(&['a', 'b'], &mut matches);
+        assert_eq!(matches, vec![1, 0]);
+    }
+}